@@ -0,0 +1,34 @@
+//! 列出几秒内加入的设备后退出，持续监听设备事件请使用 `pmctl monitor`
+use std::time::Duration;
+
+use futures::StreamExt;
+use peripheral_manager::{
+    api::PeripheralApi,
+    core::{App, AppOptions},
+    enums::EventKind,
+};
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let options = AppOptions::new()
+        .set_usb_filter(Box::new(|x| x.vendor_id == 0x3373 && x.input_report_byte_length == 65))
+        .set_best_effort(true);
+    let app = App::start(Some(options)).await?;
+
+    // 先回放适配器状态和已加入的设备，BLE 设备需要时间扫描连接
+    let mut events = app.watch();
+    let _ = tokio::time::timeout(Duration::from_secs(3), async {
+        while let Some(event) = events.next().await {
+            match event.kind {
+                EventKind::DeviceAdd(device) => println!(
+                    "{} {} {:04x}:{:04x} {} {}",
+                    device.id(), device.conn_type(), device.vendor_id(), device.product_id(),
+                    device.device_type(), device.firmware_version(),
+                ),
+                EventKind::AdapterStateChanged(state) => eprintln!("{:?} adapter: {:?}", event.conn_type, state),
+                _ => {},
+            }
+        }
+    }).await;
+    Ok(())
+}
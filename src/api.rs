@@ -2,14 +2,16 @@
 use async_trait::async_trait;
 use uuid::Uuid;
 use anyhow::Result;
-use std::fmt::Debug;
+use std::{fmt::Debug, sync::Arc};
 use crate::{
-    enums::{ChipType,ConnectionType, ChipManufacturer, DeviceType}
+    enums::{ChipType,ConnectionType, ChipManufacturer, DeviceType, PeripheralState},
+    version::Version,
 };
 
 
+/// 设备通用接口，对象安全，内置 Peripheral、LogicalDevice 与自定义设备可统一存放为 `Arc<dyn PeripheralApi>`
 #[async_trait]
-pub trait PeripheralApi: Send + Sync + Clone + Debug {
+pub trait PeripheralApi: Send + Sync + Debug {
     /// 返回设备里记录的id uuid,None 为未经过认证的
     fn id(&self) -> Uuid;
     /// 返回设备的地址
@@ -24,10 +26,15 @@ pub trait PeripheralApi: Send + Sync + Clone + Debug {
     fn device_type(&self) -> DeviceType;
     fn device_name(&self) -> String;
     fn chip_type(&self) -> ChipType;
-    fn software_version(&self) -> String;
-    fn hardware_version(&self) -> String;
-    fn firmware_version(&self) -> String;
-    /// 根据设备的uuid连接设备
+    /// 版本号可直接与字符串比较，如 `dev.firmware_version() < "1.4.0"`
+    fn software_version(&self) -> Version;
+    fn hardware_version(&self) -> Version;
+    fn firmware_version(&self) -> Version;
+    /// 返回设备的序列号，未提供时为空
+    fn serial_number(&self) -> String;
+    /// 返回设备的连接状态
+    fn state(&self) -> PeripheralState;
+    /// 根据设备的uuid重新连接设备，uuid 必须与 id() 一致
     async fn connect(&self,u: Uuid) -> Result<()>;
     /// 读取设备的数据
     async fn read<'a>(&'a self, buf: &'a mut [u8]) -> Result<usize>;
@@ -35,7 +42,133 @@ pub trait PeripheralApi: Send + Sync + Clone + Debug {
     async fn write<'a>(&'a self, src: &'a [u8]) -> Result<usize>;
     /// 发起一次请求，直接返回数据
     async fn request<'a>(&'a self, src: &'a [u8]) -> Result<Vec<u8>>;
-    /// 断开设备的连接
-    async fn disconnect(&mut self) -> Result<()>;
+    /// 断开设备的连接，USB 释放 HID 句柄，BLE 断开 GATT 连接并停止 notify 线程。
+    /// 主动断开的设备不会自动重连，保留在设备集合中直到调用 connect 或被移除
+    async fn disconnect(&self) -> Result<()>;
+}
+
+/// 动态分发的设备
+pub type DynPeripheral = Arc<dyn PeripheralApi>;
+
+/// `Arc<T>`（包括 `Arc<dyn PeripheralApi>`）可直接用于泛型参数 `T: PeripheralApi`
+#[async_trait]
+impl<T: PeripheralApi + ?Sized> PeripheralApi for Arc<T> {
+    fn id(&self) -> Uuid {
+        (**self).id()
+    }
+    fn address(&self) -> String {
+        (**self).address()
+    }
+    fn conn_type(&self) -> ConnectionType {
+        (**self).conn_type()
+    }
+    fn vendor_id(&self) -> u16 {
+        (**self).vendor_id()
+    }
+    fn product_id(&self) -> u16 {
+        (**self).product_id()
+    }
+    fn chip_manufacturer(&self) -> ChipManufacturer {
+        (**self).chip_manufacturer()
+    }
+    fn device_type(&self) -> DeviceType {
+        (**self).device_type()
+    }
+    fn device_name(&self) -> String {
+        (**self).device_name()
+    }
+    fn chip_type(&self) -> ChipType {
+        (**self).chip_type()
+    }
+    fn software_version(&self) -> Version {
+        (**self).software_version()
+    }
+    fn hardware_version(&self) -> Version {
+        (**self).hardware_version()
+    }
+    fn firmware_version(&self) -> Version {
+        (**self).firmware_version()
+    }
+    fn serial_number(&self) -> String {
+        (**self).serial_number()
+    }
+    fn state(&self) -> PeripheralState {
+        (**self).state()
+    }
+    async fn connect(&self, u: Uuid) -> Result<()> {
+        (**self).connect(u).await
+    }
+    async fn read<'a>(&'a self, buf: &'a mut [u8]) -> Result<usize> {
+        (**self).read(buf).await
+    }
+    async fn write<'a>(&'a self, src: &'a [u8]) -> Result<usize> {
+        (**self).write(src).await
+    }
+    async fn request<'a>(&'a self, src: &'a [u8]) -> Result<Vec<u8>> {
+        (**self).request(src).await
+    }
+    async fn disconnect(&self) -> Result<()> {
+        (**self).disconnect().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    /// 回显设备
+    #[derive(Debug)]
+    struct MockPeripheral {
+        state: Mutex<PeripheralState>,
+    }
+
+    #[async_trait]
+    impl PeripheralApi for MockPeripheral {
+        fn id(&self) -> Uuid { Uuid::nil() }
+        fn address(&self) -> String { "mock".to_string() }
+        fn conn_type(&self) -> ConnectionType { ConnectionType::USB }
+        fn vendor_id(&self) -> u16 { 0x3373 }
+        fn product_id(&self) -> u16 { 0x0001 }
+        fn chip_manufacturer(&self) -> ChipManufacturer { ChipManufacturer::Unknown(None) }
+        fn device_type(&self) -> DeviceType { DeviceType::Other }
+        fn device_name(&self) -> String { "mock".to_string() }
+        fn chip_type(&self) -> ChipType { ChipType::Unknown }
+        fn software_version(&self) -> Version { Version::from_raw("1.0.0") }
+        fn hardware_version(&self) -> Version { Version::from_raw("1.0.0") }
+        fn firmware_version(&self) -> Version { Version::from_raw("1.0.0") }
+        fn serial_number(&self) -> String { String::new() }
+        fn state(&self) -> PeripheralState { *self.state.lock().unwrap() }
+        async fn connect(&self, _u: Uuid) -> Result<()> {
+            *self.state.lock().unwrap() = PeripheralState::Connected;
+            Ok(())
+        }
+        async fn read<'a>(&'a self, _buf: &'a mut [u8]) -> Result<usize> { Ok(0) }
+        async fn write<'a>(&'a self, src: &'a [u8]) -> Result<usize> { Ok(src.len()) }
+        async fn request<'a>(&'a self, src: &'a [u8]) -> Result<Vec<u8>> { Ok(src.to_vec()) }
+        async fn disconnect(&self) -> Result<()> {
+            *self.state.lock().unwrap() = PeripheralState::Disconnected;
+            Ok(())
+        }
+    }
+
+    async fn echo<T: PeripheralApi>(device: &T) -> Result<Vec<u8>> {
+        device.request(&[1, 2, 3]).await
+    }
+
+    #[test]
+    fn dyn_collection() {
+        let devices: Vec<DynPeripheral> = vec![
+            Arc::new(MockPeripheral { state: Mutex::new(PeripheralState::Connected) }),
+        ];
+        tokio_test::block_on(async {
+            for device in &devices {
+                assert_eq!(echo(device).await.unwrap(), vec![1, 2, 3]);
+                device.disconnect().await.unwrap();
+                assert_eq!(device.state(), PeripheralState::Disconnected);
+            }
+        });
+    }
 }
 
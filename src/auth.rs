@@ -0,0 +1,134 @@
+use std::collections::HashMap;
+
+use anyhow::{Result, anyhow, bail};
+use async_trait::async_trait;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use crate::{
+    api::PeripheralApi,
+    peripheral::Peripheral,
+};
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// 认证命令字
+const AUTH_COMMAND: u8 = 0xA5;
+/// 随机数长度
+const NONCE_LEN: usize = 16;
+/// 设备返回的签名长度（HMAC-SHA256 截断）
+const TAG_LEN: usize = 16;
+/// 认证帧长度，USB HID 最大 64 字节
+const FRAME_LEN: usize = 64;
+
+/// 认证结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AuthResult {
+    /// 认证通过
+    Accepted,
+    /// 认证失败，附带原因
+    Rejected(String),
+}
+
+/// 设备身份认证器，在设备通过过滤之后、发送 DeviceAdd 之前执行
+#[async_trait]
+pub trait Authenticator: Send + Sync {
+    async fn authenticate(&self, device: &Peripheral) -> Result<AuthResult>;
+}
+
+/// 基于 HMAC-SHA256 的挑战应答认证，密钥按产品线（VID/PID）区分
+///
+/// 帧格式：`[AUTH_COMMAND, nonce(16)]`，设备返回 `[AUTH_COMMAND, tag(16)]`，
+/// tag 为 HMAC-SHA256(key, nonce) 的前 16 字节。
+#[derive(Default)]
+pub struct HmacAuthenticator {
+    /// (vid, pid) -> key，pid 为 None 时对整个厂商生效
+    keys: HashMap<(u16, Option<u16>), Vec<u8>>,
+}
+
+impl HmacAuthenticator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 设置某个产品的密钥
+    pub fn set_key(mut self, vid: u16, pid: u16, key: &[u8]) -> Self {
+        self.keys.insert((vid, Some(pid)), key.to_vec());
+        self
+    }
+
+    /// 设置某个厂商所有产品的默认密钥
+    pub fn set_vendor_key(mut self, vid: u16, key: &[u8]) -> Self {
+        self.keys.insert((vid, None), key.to_vec());
+        self
+    }
+
+    fn key(&self, vid: u16, pid: u16) -> Option<&Vec<u8>> {
+        self.keys.get(&(vid, Some(pid))).or_else(|| self.keys.get(&(vid, None)))
+    }
+
+    fn verify(key: &[u8], nonce: &[u8], response: &[u8]) -> Result<bool> {
+        if response.len() < 1 + TAG_LEN || response[0] != AUTH_COMMAND {
+            bail!("invalid auth response");
+        }
+        let mut mac = HmacSha256::new_from_slice(key)?;
+        mac.update(nonce);
+        Ok(mac.verify_truncated_left(&response[1..1 + TAG_LEN]).is_ok())
+    }
+}
+
+#[async_trait]
+impl Authenticator for HmacAuthenticator {
+    async fn authenticate(&self, device: &Peripheral) -> Result<AuthResult> {
+        let key = match self.key(device.vendor_id(), device.product_id()) {
+            Some(key) => key,
+            None => return Ok(AuthResult::Rejected(format!(
+                "no key for {:04x}:{:04x}", device.vendor_id(), device.product_id()
+            ))),
+        };
+        let mut nonce = [0u8; NONCE_LEN];
+        getrandom::getrandom(&mut nonce).map_err(|e| anyhow!("generate nonce: {}", e))?;
+        let mut frame = [0u8; FRAME_LEN];
+        frame[0] = AUTH_COMMAND;
+        frame[1..1 + NONCE_LEN].copy_from_slice(&nonce);
+
+        let response = device.request(&frame).await?;
+        if Self::verify(key, &nonce, &response)? {
+            Ok(AuthResult::Accepted)
+        } else {
+            Ok(AuthResult::Rejected("signature mismatch".to_string()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sign(key: &[u8], nonce: &[u8]) -> Vec<u8> {
+        let mut mac = HmacSha256::new_from_slice(key).unwrap();
+        mac.update(nonce);
+        let mut response = vec![AUTH_COMMAND];
+        response.extend_from_slice(&mac.finalize().into_bytes()[..TAG_LEN]);
+        response
+    }
+
+    #[test]
+    fn verify_response() {
+        let nonce = [7u8; NONCE_LEN];
+        let response = sign(b"secret", &nonce);
+        assert!(HmacAuthenticator::verify(b"secret", &nonce, &response).unwrap());
+        assert!(!HmacAuthenticator::verify(b"other", &nonce, &response).unwrap());
+        assert!(HmacAuthenticator::verify(b"secret", &nonce, &response[..4]).is_err());
+    }
+
+    #[test]
+    fn key_lookup() {
+        let auth = HmacAuthenticator::new()
+            .set_vendor_key(0x3373, b"vendor")
+            .set_key(0x3373, 0x0001, b"product");
+        assert_eq!(auth.key(0x3373, 0x0001).unwrap(), b"product");
+        assert_eq!(auth.key(0x3373, 0x0002).unwrap(), b"vendor");
+        assert!(auth.key(0x1234, 0x0001).is_none());
+    }
+}
@@ -0,0 +1,241 @@
+//! 同步接口，内部持有独立的 Tokio 运行时，供非 async 程序使用
+//!
+//! ```no_run
+//! use peripheral_manager::{blocking::BlockingApp, core::AppOptions};
+//!
+//! let app = BlockingApp::start(Some(AppOptions::new())).unwrap();
+//! for device in app.peripherals().unwrap() {
+//!     println!("{:?}", device.request(&[0x01]).unwrap());
+//! }
+//! // 事件迭代器不会自行结束，需要时用 recv_timeout 或 break 退出
+//! for event in app.events() {
+//!     println!("{:?}", event.kind);
+//! }
+//! ```
+use std::{future::Future, sync::Arc, time::Duration};
+
+use anyhow::Result;
+use futures::stream::StreamExt;
+use tokio::{runtime::Runtime, time};
+use uuid::Uuid;
+
+use crate::{
+    api::PeripheralApi,
+    core::{App, AppOptions},
+    enums::{CoreEvent, Error, InfoChange, PeripheralState},
+    peripheral::{Peripheral, PeripheralInfo},
+    stream::EventStream,
+};
+
+/// 默认操作超时时间
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 在运行时上执行，超时返回 Error::TimedOut
+fn block_on<T, F>(runtime: &Runtime, timeout: Duration, f: F) -> Result<T>
+where
+    F: Future<Output = Result<T>>,
+{
+    runtime.block_on(async {
+        time::timeout(timeout, f).await.map_err(|_| Error::TimedOut(timeout))?
+    })
+}
+
+/// App 的同步封装
+pub struct BlockingApp {
+    // app 必须先于运行时释放
+    app: App,
+    runtime: Arc<Runtime>,
+    timeout: Duration,
+}
+
+impl BlockingApp {
+    /// 创建运行时并启动 App
+    pub fn start(options: Option<AppOptions>) -> Result<Self> {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()?;
+        let app = runtime.block_on(App::start(options))?;
+        Ok(BlockingApp {
+            app,
+            runtime: Arc::new(runtime),
+            timeout: DEFAULT_TIMEOUT,
+        })
+    }
+
+    /// 设置读写等操作的超时时间，之后获取的设备使用该超时
+    pub fn set_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// 获取所有的外围设备
+    pub fn peripherals(&self) -> Result<Vec<BlockingPeripheral>> {
+        let peripherals = block_on(&self.runtime, self.timeout, self.app.peripherals())?;
+        Ok(peripherals.into_iter().map(|p| self.wrap(p)).collect())
+    }
+
+    /// 根据id 获取外围设备
+    pub fn peripheral(&self, id: &Uuid) -> Result<BlockingPeripheral> {
+        let peripheral = block_on(&self.runtime, self.timeout, self.app.peripheral(id))?;
+        Ok(self.wrap(peripheral))
+    }
+
+    /// 阻塞的事件迭代器
+    pub fn events(&self) -> BlockingEvents {
+        BlockingEvents {
+            stream: self.app.events(),
+            runtime: Arc::clone(&self.runtime),
+        }
+    }
+
+    /// 先回放各适配器当前状态的 AdapterStateChanged 和当前所有设备的 DeviceAdd，再接收之后的事件
+    pub fn watch(&self) -> BlockingEvents {
+        BlockingEvents {
+            stream: self.app.watch(),
+            runtime: Arc::clone(&self.runtime),
+        }
+    }
+
+    /// 内部的异步 App
+    pub fn inner(&self) -> &App {
+        &self.app
+    }
+
+    fn wrap(&self, peripheral: Peripheral) -> BlockingPeripheral {
+        BlockingPeripheral {
+            inner: peripheral,
+            runtime: Arc::clone(&self.runtime),
+            timeout: self.timeout,
+        }
+    }
+}
+
+/// Peripheral 的同步封装，所有操作带超时
+#[derive(Clone)]
+pub struct BlockingPeripheral {
+    inner: Peripheral,
+    runtime: Arc<Runtime>,
+    timeout: Duration,
+}
+
+impl BlockingPeripheral {
+    pub fn id(&self) -> Uuid {
+        self.inner.id()
+    }
+
+    pub fn info(&self) -> PeripheralInfo {
+        self.inner.info()
+    }
+
+    pub fn state(&self) -> PeripheralState {
+        self.inner.state()
+    }
+
+    /// 读取设备的数据
+    pub fn read(&self, buf: &mut [u8]) -> Result<usize> {
+        block_on(&self.runtime, self.timeout, self.inner.read(buf))
+    }
+
+    /// 写入设备的数据
+    pub fn write(&self, src: &[u8]) -> Result<usize> {
+        block_on(&self.runtime, self.timeout, self.inner.write(src))
+    }
+
+    /// 发起一次请求，直接返回数据
+    pub fn request(&self, src: &[u8]) -> Result<Vec<u8>> {
+        block_on(&self.runtime, self.timeout, self.inner.request(src))
+    }
+
+    /// 重新连接设备
+    pub fn connect(&self) -> Result<()> {
+        block_on(&self.runtime, self.timeout, self.inner.connect(self.inner.id()))
+    }
+
+    /// 断开设备的连接
+    pub fn disconnect(&self) -> Result<()> {
+        block_on(&self.runtime, self.timeout, self.inner.disconnect())
+    }
+
+    /// 重新读取设备信息
+    pub fn refresh_info(&self) -> Result<Vec<InfoChange>> {
+        block_on(&self.runtime, self.timeout, self.inner.refresh_info())
+    }
+
+    /// 内部的异步 Peripheral
+    pub fn inner(&self) -> &Peripheral {
+        &self.inner
+    }
+}
+
+/// 阻塞的事件迭代器
+///
+/// App 的后台任务一直持有事件通道，迭代器不会自行结束，
+/// 需要退出循环时使用 recv_timeout 或在循环内 break。
+pub struct BlockingEvents {
+    stream: EventStream,
+    runtime: Arc<Runtime>,
+}
+
+impl BlockingEvents {
+    /// 等待下一个事件，超时返回 None
+    pub fn recv_timeout(&mut self, timeout: Duration) -> Option<CoreEvent> {
+        let stream = &mut self.stream;
+        self.runtime.block_on(async { time::timeout(timeout, stream.next()).await.ok().flatten() })
+    }
+}
+
+impl Iterator for BlockingEvents {
+    type Item = CoreEvent;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let stream = &mut self.stream;
+        self.runtime.block_on(stream.next())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::sync::broadcast;
+
+    use super::*;
+    use crate::{
+        enums::{AdapterState, ConnectionType, EventKind},
+        registry::Registry,
+    };
+
+    fn runtime() -> Arc<Runtime> {
+        Arc::new(tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap())
+    }
+
+    #[test]
+    fn block_on_timeout() {
+        let runtime = runtime();
+        let value = block_on(&runtime, Duration::from_millis(100), async { Ok(1) }).unwrap();
+        assert_eq!(value, 1);
+
+        let err = block_on(&runtime, Duration::from_millis(10), async {
+            time::sleep(Duration::from_secs(1)).await;
+            Ok(())
+        }).unwrap_err();
+        assert!(matches!(err.downcast_ref::<Error>(), Some(Error::TimedOut(_))));
+    }
+
+    #[test]
+    fn events_recv_timeout() {
+        let runtime = runtime();
+        let (sender, receiver) = broadcast::channel(8);
+        let mut events = BlockingEvents {
+            stream: EventStream::new(receiver, Arc::new(Registry::new(ConnectionType::USB))),
+            runtime,
+        };
+        assert!(events.recv_timeout(Duration::from_millis(10)).is_none());
+
+        sender.send(CoreEvent::new(ConnectionType::USB, EventKind::AdapterStateChanged(AdapterState::Available))).unwrap();
+        let event = events.recv_timeout(Duration::from_millis(10)).unwrap();
+        assert!(matches!(event.kind, EventKind::AdapterStateChanged(AdapterState::Available)));
+
+        // 发送端全部释放后迭代结束
+        drop(sender);
+        assert!(events.next().is_none());
+    }
+}
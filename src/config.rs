@@ -0,0 +1,271 @@
+use std::{env, path::Path, str::FromStr, time::Duration};
+
+use anyhow::{Result, anyhow, bail};
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::{
+    core::AppOptions,
+    enums::ConnectionType,
+    filter::FilterRules,
+    reconnect::ReconnectOptions,
+};
+
+/// 环境变量覆盖前缀，`__` 表示下一级，如 `PERIPHERAL_MANAGER_FILTERS__USB`
+pub const ENV_PREFIX: &str = "PERIPHERAL_MANAGER_";
+
+/// 可由环境变量覆盖的配置项，其他带前缀的环境变量忽略
+const ENV_KEYS: &[&str] = &[
+    "broadcast",
+    "broadcast_buf_len",
+    "debounce_ms",
+    "preferred_link",
+    "request_timeout_ms",
+    "auth_timeout_ms",
+    "reconnect",
+    "reconnect__max_retries",
+    "reconnect__initial_delay_ms",
+    "reconnect__max_delay_ms",
+    "filters",
+    "filters__usb",
+    "filters__ble",
+    "enable_usb",
+    "enable_ble",
+    "best_effort",
+];
+
+/// 配置文件格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigFormat {
+    Toml,
+    Json,
+}
+
+impl ConfigFormat {
+    /// 按扩展名判断格式
+    pub fn from_path(path: &Path) -> Result<Self> {
+        match path.extension().and_then(|e| e.to_str()).map(str::to_ascii_lowercase).as_deref() {
+            Some("toml") => Ok(ConfigFormat::Toml),
+            Some("json") => Ok(ConfigFormat::Json),
+            _ => bail!("unsupported config file: {}", path.display()),
+        }
+    }
+}
+
+/// 配置文件内容，未出现的字段使用默认值
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct AppConfig {
+    broadcast: bool,
+    broadcast_buf_len: usize,
+    /// 热插拔去抖窗口，毫秒
+    debounce_ms: Option<u64>,
+    /// `USB` 或 `BLE`
+    preferred_link: Option<String>,
+    /// BLE request 等待应答的超时，毫秒
+    request_timeout_ms: Option<u64>,
+    /// 设备认证超时，毫秒
+    auth_timeout_ms: Option<u64>,
+    reconnect: Option<ReconnectConfig>,
+    filters: FilterRules,
+    enable_usb: bool,
+    enable_ble: bool,
+    best_effort: bool,
+}
+
+impl Default for AppConfig {
+    fn default() -> Self {
+        AppConfig {
+            broadcast: true,
+            broadcast_buf_len: 108,
+            debounce_ms: None,
+            preferred_link: None,
+            request_timeout_ms: None,
+            auth_timeout_ms: None,
+            reconnect: None,
+            filters: FilterRules::default(),
+            enable_usb: true,
+            enable_ble: true,
+            best_effort: false,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ReconnectConfig {
+    max_retries: u32,
+    initial_delay_ms: u64,
+    max_delay_ms: u64,
+}
+
+impl Default for ReconnectConfig {
+    fn default() -> Self {
+        let options = ReconnectOptions::default();
+        ReconnectConfig {
+            max_retries: options.max_retries,
+            initial_delay_ms: options.initial_delay.as_millis() as u64,
+            max_delay_ms: options.max_delay.as_millis() as u64,
+        }
+    }
+}
+
+impl AppOptions {
+    /// 从 TOML/JSON 配置文件加载，格式按扩展名判断，环境变量覆盖文件中的值
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let format = ConfigFormat::from_path(path)?;
+        let content = std::fs::read_to_string(path).map_err(|e| anyhow!("{}: {}", path.display(), e))?;
+        Self::from_str(&content, format)
+    }
+
+    /// 从 TOML/JSON 字符串加载，环境变量覆盖其中的值
+    pub fn from_str(content: &str, format: ConfigFormat) -> Result<Self> {
+        Self::from_value(parse(content, format)?, env::vars())
+    }
+
+    fn from_value(mut value: Value, vars: impl Iterator<Item = (String, String)>) -> Result<Self> {
+        let mut overrides: Vec<_> = vars
+            .filter_map(|(key, v)| Some((key.strip_prefix(ENV_PREFIX)?.to_string(), v)))
+            .filter(|(key, _)| ENV_KEYS.contains(&key.to_ascii_lowercase().as_str()))
+            .collect();
+        // 上一级先覆盖，下一级（`__`）再覆盖其中的值，结果与环境变量的顺序无关
+        overrides.sort_by_key(|(key, _)| (key.matches("__").count(), key.to_ascii_lowercase()));
+        for (key, v) in overrides {
+            override_value(&mut value, &key, &v)?;
+        }
+        let config: AppConfig = serde_path_to_error::deserialize(value)
+            .map_err(|e| anyhow!("invalid config at `{}`: {}", e.path(), e.inner()))?;
+        config.into_options()
+    }
+}
+
+impl AppConfig {
+    fn into_options(self) -> Result<AppOptions> {
+        if let Some(rule) = &self.filters.usb {
+            rule.validate().map_err(|e| anyhow!("invalid config at `filters.usb`: {}", e))?;
+        }
+        if let Some(rule) = &self.filters.ble {
+            rule.validate().map_err(|e| anyhow!("invalid config at `filters.ble`: {}", e))?;
+        }
+        let mut options = AppOptions::new()
+            .set_broadcast(self.broadcast, self.broadcast_buf_len)
+            .set_transports(self.enable_usb, self.enable_ble)
+            .set_best_effort(self.best_effort)
+            .set_filter_rules(self.filters)?;
+        if let Some(link) = self.preferred_link {
+            let link = ConnectionType::from_str(&link.to_ascii_uppercase())
+                .map_err(|_| anyhow!("invalid config at `preferred_link`: expected USB or BLE, got {:?}", link))?;
+            options = options.set_preferred_link(link);
+        }
+        if let Some(ms) = self.debounce_ms {
+            options = options.set_debounce(Duration::from_millis(ms));
+        }
+        if let Some(ms) = self.request_timeout_ms {
+            options = options.set_request_timeout(Duration::from_millis(ms));
+        }
+        if let Some(ms) = self.auth_timeout_ms {
+            options = options.set_auth_timeout(Duration::from_millis(ms));
+        }
+        if let Some(r) = self.reconnect {
+            if r.initial_delay_ms > r.max_delay_ms {
+                bail!("invalid config at `reconnect.initial_delay_ms`: greater than max_delay_ms");
+            }
+            options = options.set_auto_reconnect(ReconnectOptions {
+                max_retries: r.max_retries,
+                initial_delay: Duration::from_millis(r.initial_delay_ms),
+                max_delay: Duration::from_millis(r.max_delay_ms),
+            });
+        }
+        Ok(options)
+    }
+}
+
+fn parse(content: &str, format: ConfigFormat) -> Result<Value> {
+    let value = match format {
+        ConfigFormat::Toml => toml::from_str::<Value>(content)?,
+        ConfigFormat::Json => serde_json::from_str::<Value>(content)?,
+    };
+    if !value.is_object() {
+        bail!("config root must be a table");
+    }
+    Ok(value)
+}
+
+/// 按 `A__B` 路径写入值，值按 JSON 解析，失败时作为字符串
+fn override_value(root: &mut Value, key: &str, v: &str) -> Result<()> {
+    let mut node = root;
+    let path: Vec<String> = key.split("__").map(str::to_ascii_lowercase).collect();
+    for (i, name) in path.iter().enumerate() {
+        let map = node.as_object_mut().ok_or_else(|| anyhow!("invalid env override {}{}: `{}` is not a table", ENV_PREFIX, key, path[..i].join(".")))?;
+        if i + 1 == path.len() {
+            map.insert(name.clone(), serde_json::from_str(v).unwrap_or_else(|_| Value::String(v.to_string())));
+            return Ok(());
+        }
+        node = map.entry(name.clone()).or_insert_with(|| Value::Object(Default::default()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load(content: &str, format: ConfigFormat, vars: &[(&str, &str)]) -> Result<AppOptions> {
+        let vars = vars.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect::<Vec<_>>();
+        AppOptions::from_value(parse(content, format)?, vars.into_iter())
+    }
+
+    #[test]
+    fn error_points_at_key() {
+        let toml = r#"
+            broadcast_buf_len = 10
+            [filters.usb.all]
+        "#;
+        let err = load(toml, ConfigFormat::Toml, &[]).unwrap_err().to_string();
+        assert!(err.contains("filters.usb"), "{}", err);
+
+        let json = r#"{ "reconnect": { "max_retries": "ten" } }"#;
+        let err = load(json, ConfigFormat::Json, &[]).unwrap_err().to_string();
+        assert!(err.contains("reconnect.max_retries"), "{}", err);
+
+        let err = load(r#"{ "brodcast": true }"#, ConfigFormat::Json, &[]).unwrap_err().to_string();
+        assert!(err.contains("brodcast"), "{}", err);
+    }
+
+    #[test]
+    fn env_override() {
+        let toml = r#"
+            debounce_ms = 200
+            [filters]
+            usb = { vendor_id = 13171 }
+        "#;
+        assert!(load(toml, ConfigFormat::Toml, &[]).is_ok());
+        let vars = [("PERIPHERAL_MANAGER_FILTERS__BLE", r#"{"name_regex": "("}"#)];
+        let err = load(toml, ConfigFormat::Toml, &vars).unwrap_err().to_string();
+        assert!(err.contains("filters.ble"), "{}", err);
+        let vars = [("PERIPHERAL_MANAGER_PREFERRED_LINK", "bluetooth")];
+        let err = load(toml, ConfigFormat::Toml, &vars).unwrap_err().to_string();
+        assert!(err.contains("preferred_link"), "{}", err);
+        let vars = [("PERIPHERAL_MANAGER_REQUEST_TIMEOUT_MS", "soon")];
+        let err = load(toml, ConfigFormat::Toml, &vars).unwrap_err().to_string();
+        assert!(err.contains("request_timeout_ms"), "{}", err);
+        // 下一级在上一级之后覆盖，与环境变量的顺序无关
+        let vars = [
+            ("PERIPHERAL_MANAGER_FILTERS__BLE", r#"{"name_regex": "("}"#),
+            ("PERIPHERAL_MANAGER_FILTERS", "{}"),
+        ];
+        let err = load(toml, ConfigFormat::Toml, &vars).unwrap_err().to_string();
+        assert!(err.contains("filters.ble"), "{}", err);
+    }
+
+    #[test]
+    fn unrelated_env_ignored() {
+        let vars = [
+            ("PERIPHERAL_MANAGER_LOG_LEVEL", "debug"),
+            ("PERIPHERAL_MANAGER_RECONNECT__JITTER", "1"),
+            ("PERIPHERAL_MANAGER_AUTH_TIMEOUT_MS", "500"),
+        ];
+        assert!(load("debounce_ms = 200", ConfigFormat::Toml, &vars).is_ok());
+    }
+}
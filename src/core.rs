@@ -1,10 +1,10 @@
 use anyhow::{Result, bail, anyhow};
-use dashmap::DashMap;
-use std::{sync::Arc,pin::Pin, str::FromStr, f32::consts::E};
+use std::{collections::HashMap, sync::{Arc, RwLock},pin::Pin, str::FromStr, f32::consts::E, time::Duration, future::Future};
 use uuid::Uuid;
-use tokio::sync::{broadcast,broadcast::Receiver,broadcast::Sender, Mutex };
+use tokio::{sync::{broadcast,broadcast::Receiver,broadcast::Sender, Mutex }, time};
 use tokio_stream::Stream;
 use futures::stream::StreamExt;
+use tokio_util::sync::CancellationToken;
 
 use usb_manager::{
     hid_device::HidDevice,
@@ -19,17 +19,31 @@ use btleplug::{
     api::{
         Central,
         CentralEvent as BleCentralEvent, 
+        CentralState,
+        Peripheral as _,
     }
 };
 
 use super::{
     api::PeripheralApi,
-    peripheral::Peripheral,
-    enums::CoreEvent,
+    auth::{Authenticator, AuthResult},
+    peripheral::{DEFAULT_REQUEST_TIMEOUT, Peripheral, UsbDescriptor, ble_identity, ble_uuid, usb_identity},
+    reconnect::{DisconnectAction, ReconnectOptions, Reconnector},
+    stream::EventStream,
+    registry::Registry,
+    debounce::Debouncer,
+    tasks::KeyedTasks,
+    handler::spawn_device_handler,
+    matcher::DeviceMatcher,
+    logical::{IdentityResolver, LogicalDevice},
+    filter::{FilterRules, Filters},
+    probe::ProbeFilter,
+    profile::ProfileRegistry,
+    enums::{AdapterState, ConnectionType, CoreEvent, Error, EventKind, RemoveReason},
 };
 
 
-pub type UsbFilterHandler = Box<dyn Fn(&HidDevice) -> bool + Send  + Sync>;
+pub type UsbFilterHandler = Box<dyn Fn(&UsbDescriptor) -> bool + Send  + Sync>;
 pub type BleFilterHandler = Box<dyn Fn(&BlePeripheral) -> bool + Send  + Sync>;
 
 /// 初始化配置参数
@@ -38,8 +52,32 @@ pub struct AppOptions
 {
     is_broadcast: bool,
     broadcast_buf_len: usize,
-    ble_filter: Option<BleFilterHandler>,
-    usb_filter:Option<UsbFilterHandler>,
+    /// 过滤函数和声明式规则，App::set_filters 时整体替换
+    filters: RwLock<Filters>,
+    /// 型号表，覆盖默认识别的芯片、设备类型和名称
+    profiles: Arc<ProfileRegistry>,
+    /// 探测过滤及时间预算，过滤之后、认证之前执行
+    probe: Option<(Box<dyn ProbeFilter>, Duration)>,
+    /// 设备身份认证，探测之后执行
+    authenticator: Option<Box<dyn Authenticator>>,
+    /// 认证超时，超时视为认证失败，None 为不限制
+    auth_timeout: Option<Duration>,
+    /// BLE request 等待应答的超时
+    request_timeout: Duration,
+    /// BLE 自动重连，None 为断开即移除
+    reconnect: Option<ReconnectOptions>,
+    /// 热插拔去抖窗口，None 为不去抖
+    debounce: Option<Duration>,
+    /// 逻辑设备的首选连接类型
+    preferred_link: ConnectionType,
+    /// 是否启用 USB
+    enable_usb: bool,
+    /// 是否启用 BLE
+    enable_ble: bool,
+    /// 适配器启动失败时只发送 AdapterStateChanged，其他连接类型继续工作
+    best_effort: bool,
+    /// 逻辑设备标识解析，None 时使用 VID + 序列号
+    identity_resolver: Option<Box<dyn IdentityResolver>>,
 }
 
 impl Default for AppOptions{
@@ -47,25 +85,81 @@ impl Default for AppOptions{
         AppOptions {
             is_broadcast: true,
             broadcast_buf_len: 108,
-            ble_filter:None,
-            usb_filter:None,
+            filters:RwLock::new(Filters::default()),
+            profiles:Arc::new(ProfileRegistry::builtin()),
+            probe:None,
+            authenticator:None,
+            auth_timeout:None,
+            request_timeout:DEFAULT_REQUEST_TIMEOUT,
+            reconnect:None,
+            debounce:None,
+            preferred_link:ConnectionType::USB,
+            enable_usb:true,
+            enable_ble:true,
+            best_effort:false,
+            identity_resolver:None,
         }
     }
 }
 
 impl AppOptions{
-    fn usb_filter(&self, hid_device: &HidDevice) -> bool {
-        if let Some(filter_handler) = &self.usb_filter {
-            return filter_handler(hid_device);
-        }
-        true
+    fn usb_filter(&self, descriptor: &UsbDescriptor) -> bool {
+        self.filters.read().unwrap().matches_usb(descriptor)
     }
     fn ble_filter(&self, ble_device: &BlePeripheral) -> bool {
-        if let Some(filter_handler) = &self.ble_filter {
-            return filter_handler(ble_device);
+        self.filters.read().unwrap().matches_ble(ble_device)
+    }
+    /// 已识别的设备是否满足当前过滤条件
+    fn filter_identified(&self, peripheral: &Peripheral) -> bool {
+        self.filters.read().unwrap().matches(peripheral)
+    }
+    /// 逻辑设备标识，解析失败或没有序列号时返回 None
+    ///
+    /// 序列号只在同一厂商内唯一（也可能是占位值），加上 VID 避免不同厂商的设备被合并；
+    /// USB 和 BLE 的 PID 可能不同，不参与合并
+    async fn logical_key(&self, device: &Peripheral) -> Option<String> {
+        if let Some(resolver) = &self.identity_resolver {
+            match resolver.unique_id(device).await {
+                Ok(Some(key)) => return Some(key),
+                Ok(None) => {},
+                Err(e) => println!("resolve identity error:{:?}",e),
+            }
+        }
+        Some(device.serial_number()).filter(|s| !s.is_empty())
+            .map(|serial| format!("{:04x}:{}", device.vendor_id(), serial))
+    }
+    /// 探测超时或出错时视为拒绝
+    async fn probe(&self, device: &Peripheral) -> bool {
+        if let Some((probe, budget)) = &self.probe {
+            return match time::timeout(*budget, probe.probe(device, *budget)).await {
+                Ok(Ok(accepted)) => accepted,
+                Ok(Err(e)) => {
+                    println!("probe {} error:{:?}",device.id(),e);
+                    false
+                },
+                Err(_) => {
+                    println!("probe {} timed out",device.id());
+                    false
+                },
+            };
         }
         true
     }
+    /// 认证器出错或超时时视为认证失败
+    async fn authenticate(&self, device: &Peripheral) -> AuthResult {
+        if let Some(authenticator) = &self.authenticator {
+            let result = match self.auth_timeout {
+                Some(timeout) => time::timeout(timeout, authenticator.authenticate(device)).await
+                    .unwrap_or_else(|_| Err(Error::TimedOut(timeout).into())),
+                None => authenticator.authenticate(device).await,
+            };
+            return match result {
+                Ok(result) => result,
+                Err(e) => AuthResult::Rejected(e.to_string()),
+            };
+        }
+        AuthResult::Accepted
+    }
     pub fn set_broadcast(mut self,is_broadcast: bool, broadcast_buf_len: usize) ->Self{
         self.is_broadcast = is_broadcast;
         self.broadcast_buf_len = broadcast_buf_len;
@@ -73,12 +167,89 @@ impl AppOptions{
     }
 
     pub fn set_usb_filter(mut self,filter_handler:UsbFilterHandler) -> Self{
-        self.usb_filter = Some(filter_handler);
+        self.filters.get_mut().unwrap().usb = Some(filter_handler);
         self
     }
 
     pub fn set_ble_filter(mut self,filter_handler:BleFilterHandler) -> Self{
-        self.ble_filter = Some(filter_handler);
+        self.filters.get_mut().unwrap().ble = Some(filter_handler);
+        self
+    }
+
+    /// 设置声明式过滤规则，与 set_usb_filter/set_ble_filter 同时满足才加入，
+    /// BLE 规则在读取设备信息之后执行
+    pub fn set_filter_rules(mut self,rules:FilterRules) -> Result<Self>{
+        self.filters.get_mut().unwrap().rules = rules.compile()?;
+        Ok(self)
+    }
+
+    /// 设置型号表，设备识别后和刷新设备信息后按 VID/PID、名称、固件版本查找型号。
+    /// 替换内置型号表，需要保留内置型号时使用 `ProfileRegistry::builtin().extend(profiles)`
+    pub fn set_profiles(mut self,profiles:ProfileRegistry) -> Self{
+        self.profiles = Arc::new(profiles);
+        self
+    }
+
+    /// 设置探测过滤器，设备通过过滤后以临时句柄探测，budget 内未接受的设备直接丢弃
+    pub fn set_probe_filter(mut self,probe:Box<dyn ProbeFilter>,budget:Duration) -> Self{
+        self.probe = Some((probe, budget));
+        self
+    }
+
+    /// 设置设备身份认证器，认证失败的设备发送 AuthenticationRejected 事件
+    pub fn set_authenticator(mut self,authenticator:Box<dyn Authenticator>) -> Self{
+        self.authenticator = Some(authenticator);
+        self
+    }
+
+    /// 开启 BLE 自动重连，意外断开后保留原 Peripheral 并按退避间隔重连，调用 disconnect 主动断开的设备除外
+    pub fn set_auto_reconnect(mut self,reconnect:ReconnectOptions) -> Self{
+        self.reconnect = Some(reconnect);
+        self
+    }
+
+    /// 设置热插拔去抖窗口，设备移除后在窗口期内重新加入则不发送事件，沿用原 Peripheral 并替换设备句柄。
+    /// USB 设备有序列号时按 VID/PID + 序列号识别同一设备，同一设备的多个 HID 接口只加入一个
+    pub fn set_debounce(mut self,window:Duration) -> Self{
+        self.debounce = Some(window);
+        self
+    }
+
+    /// 设置认证超时，超时的设备以 AuthenticationRejected 拒绝，默认不限制
+    pub fn set_auth_timeout(mut self,timeout:Duration) -> Self{
+        self.auth_timeout = Some(timeout);
+        self
+    }
+
+    /// 设置 BLE request 等待应答的超时，默认 2 秒
+    pub fn set_request_timeout(mut self,timeout:Duration) -> Self{
+        self.request_timeout = timeout;
+        self
+    }
+
+    /// 设置逻辑设备优先使用的连接类型，默认 USB
+    pub fn set_preferred_link(mut self,conn_type:ConnectionType) -> Self{
+        self.preferred_link = conn_type;
+        self
+    }
+
+    /// 设置启用的连接类型，默认全部启用
+    pub fn set_transports(mut self,usb:bool,ble:bool) -> Self{
+        self.enable_usb = usb;
+        self.enable_ble = ble;
+        self
+    }
+
+    /// 尽力模式，适配器（如没有蓝牙的台式机）启动失败时 App::start 不返回错误，
+    /// 而是发送 AdapterStateChanged(Unavailable)，可通过 App::adapter_state 查询
+    pub fn set_best_effort(mut self,best_effort:bool) -> Self{
+        self.best_effort = best_effort;
+        self
+    }
+
+    /// 设置逻辑设备标识解析，默认按 VID + 序列号合并同一物理设备的多个连接
+    pub fn set_identity_resolver(mut self,resolver:Box<dyn IdentityResolver>) -> Self{
+        self.identity_resolver = Some(resolver);
         self
     }
 }
@@ -88,26 +259,76 @@ impl AppOptions {
         Self { 
             is_broadcast: false, 
             broadcast_buf_len: 0, 
-            ble_filter: None,
-            usb_filter:None 
+            filters: RwLock::new(Filters::default()),
+            profiles: Arc::new(ProfileRegistry::builtin()),
+            probe: None,
+            authenticator: None,
+            auth_timeout: None,
+            request_timeout: DEFAULT_REQUEST_TIMEOUT,
+            reconnect: None,
+            debounce: None,
+            preferred_link: ConnectionType::USB,
+            enable_usb: true,
+            enable_ble: true,
+            best_effort: false,
+            identity_resolver: None,
         }
     }
 }
 
+/// 各连接类型适配器的最新状态
+#[derive(Default)]
+struct AdapterStates(RwLock<HashMap<ConnectionType, AdapterState>>);
+
+impl AdapterStates {
+    fn get(&self, conn_type: ConnectionType) -> Option<AdapterState> {
+        self.0.read().unwrap().get(&conn_type).cloned()
+    }
+
+    /// 更新状态，有变化时发送 AdapterStateChanged
+    ///
+    /// 持有写锁时发送，与 subscribe 互斥，状态快照和事件之间不会遗漏或重复
+    fn report(&self, conn_type: ConnectionType, state: AdapterState, sender: &Sender<CoreEvent>) {
+        let mut states = self.0.write().unwrap();
+        if states.insert(conn_type, state.clone()).as_ref() == Some(&state) {
+            return;
+        }
+        let _ = sender.send(CoreEvent::new(conn_type, EventKind::AdapterStateChanged(state)));
+    }
+
+    /// 在状态不变时执行 subscribe，返回当前所有状态
+    fn subscribe<T>(&self, subscribe: impl FnOnce() -> T) -> (Vec<(ConnectionType, AdapterState)>, T) {
+        let states = self.0.read().unwrap();
+        let mut snapshot: Vec<_> = states.iter()
+            .map(|(conn_type, state)| (*conn_type, state.clone()))
+            .collect();
+        snapshot.sort_by_key(|(conn_type, _)| conn_type.num());
+        (snapshot, subscribe())
+    }
+}
+
+/// 未设置广播长度时的事件缓冲长度
+const DEFAULT_EVENT_BUF_LEN: usize = 108;
+
 /// 设备
 pub struct App {
     /// 设备过滤
     options: Arc<AppOptions>,
-    /// 设备消息广播
-    announcer : Option<Sender<CoreEvent>>,
+    /// 内部事件通道，events/watch/on_device 始终可用，
+    /// is_broadcast 为 true 时才允许 register_broadcast 直接订阅
+    announcer : Sender<CoreEvent>,
     /// USB 适配器
     usb_adapter: Arc<Mutex<Option<UsbAdapter>>>,
     /// 蓝牙 适配器
     ble_adapter:Arc<Mutex<Option<BleAdapter>>>,
+    /// 适配器状态，未启用的连接类型没有状态
+    adapter_states: Arc<AdapterStates>,
+    /// 进行中的设备认证，按设备标识区分，热插拔和 set_filters 共用
+    admissions: Arc<KeyedTasks<String>>,
     /// 线程句柄
     _thread_handle: Option<tokio::task::JoinHandle<()>>,
     /// 设备集合
-    peripherals: DashMap<Uuid, Peripheral>
+    peripherals: Arc<Registry>
 }
 
 
@@ -125,98 +346,258 @@ impl App {
             Some(o) => o,
             None => AppOptions::default()
         };
-        let mut announcer = None;
-        if options.is_broadcast {
-            let (broadcast_sender, _) = broadcast::channel(options.broadcast_buf_len.clone());
-            announcer = Some(broadcast_sender);
-        }
+        let buf_len = if options.broadcast_buf_len > 0 { options.broadcast_buf_len } else { DEFAULT_EVENT_BUF_LEN };
+        let (announcer, _) = broadcast::channel(buf_len);
+        let registry = Registry::new(options.preferred_link);
         let app = Self { 
             options: Arc::new(options), 
             announcer,
             usb_adapter:Arc::new(Mutex::new(None)),
             ble_adapter:Arc::new(Mutex::new(None)),
+            adapter_states:Arc::new(AdapterStates::default()),
+            admissions:Arc::new(KeyedTasks::default()),
             _thread_handle:None, 
-            peripherals: DashMap::new(),
+            peripherals: Arc::new(registry),
         };
         Ok(app)
     }
     
-    /// 获取所有的外围设备（已过滤） 
+    /// 获取所有的外围设备（已过滤、已认证），不需要广播也可轮询
     pub async fn peripherals(&self) -> Result<Vec<Peripheral>>{
-        let adapter = self.usb_adapter.lock().await;
-        adapter.as_ref().ok_or(anyhow!("Usb Adapter is null"))?.
-        peripherals().map(|x| {
-            x.into_iter().filter(|a| self.options.usb_filter(a)).
-            map(|a| Peripheral::new_usb(a)).collect()
-        })
+        Ok(self.peripherals.list())
     }
 
     /// 根据id 获取外围设备 
     pub async fn peripheral(&self,id: &Uuid) -> Result<Peripheral>{
-        let adapter = self.usb_adapter.lock().await;
-        adapter.as_ref().ok_or(anyhow!("Usb Adapter is null"))?.
-        peripheral(id).map(|x| Peripheral::new_usb(x))
+        self.peripherals.get(id).ok_or(Error::DeviceNotFound.into())
+    }
+
+    /// 获取所有逻辑设备，同一物理设备的 USB 和 BLE 连接合并为一个，
+    /// 没有序列号且无法解析唯一 id 的设备不包含在内
+    pub fn logical_devices(&self) -> Vec<LogicalDevice>{
+        self.peripherals.logical_devices()
+    }
+
+    /// 根据标识获取逻辑设备，默认标识为 `VID:序列号`（如 `3373:A1B2C3`），设置 IdentityResolver 时为其返回的唯一 id
+    pub fn logical_device(&self,key: &str) -> Result<LogicalDevice>{
+        self.peripherals.logical_device(key).ok_or(Error::DeviceNotFound.into())
     }
 
 
     /// 启动 
+    ///
+    /// 先启动所有启用的适配器，再启动事件任务，启动失败返回错误时不会留下事件任务
     async fn run(&mut self) -> Result<()> {
-        {
-            let adapter = UsbAdapter::new();
-            adapter.start()?;
-            let mut mut_adapter = self.usb_adapter.lock().await;
-            *mut_adapter = Some(adapter);
-
-            let ble_adapter = BleAdapter::new();
-            // 设置蓝牙连接的事件监听器
-            ble_adapter.start_conn_watcher().await?;
-            let mut mut_ble_adapter = self.ble_adapter.lock().await;
-            *mut_ble_adapter = Some(ble_adapter);
-        }
-        {
+        let usb_started = self.options.enable_usb && match self.start_usb().await {
+            Ok(_) => true,
+            Err(err) => {
+                self.adapter_failed(ConnectionType::USB, err)?;
+                false
+            },
+        };
+        let ble_started = self.options.enable_ble && match self.start_ble().await {
+            Ok(_) => true,
+            Err(err) => {
+                self.adapter_failed(ConnectionType::BLE, err)?;
+                false
+            },
+        };
+        if usb_started {
             let options =Arc::clone(&self.options);
-            let sender = self.announcer.clone().ok_or(anyhow!("Usb Adapter is null"))?;
-
+            let sender = self.announcer.clone();
             let adapter_clone = Arc::clone(&self.usb_adapter);
+            let registry = Arc::clone(&self.peripherals);
+            let states = Arc::clone(&self.adapter_states);
+            let admissions = Arc::clone(&self.admissions);
             tokio::spawn(async {
-                if let Err(err) = usb_event(adapter_clone,options,sender).await {
+                if let Err(err) = usb_event(adapter_clone,options,sender,registry,states,admissions).await {
                     println!("{:?}", err);
                 }
             });
         }
-        {
+        if ble_started {
             let options =Arc::clone(&self.options);
-            let sender = self.announcer.clone().ok_or(anyhow!("Usb Adapter is null"))?;
+            let sender = self.announcer.clone();
             let ble_adapter_clone = Arc::clone(&self.ble_adapter);
+            let registry = Arc::clone(&self.peripherals);
+            let states = Arc::clone(&self.adapter_states);
+            let admissions = Arc::clone(&self.admissions);
             tokio::spawn(async {
-                if let Err(err) = ble_event(ble_adapter_clone,options,sender).await {
+                if let Err(err) = ble_event(ble_adapter_clone,options,sender,registry,states,admissions).await {
                     println!("{:?}", err);
                 }
             });
         }
+        Ok(())
+    }
+
+    /// 启动 USB 适配器，加入启动前已插入的设备
+    async fn start_usb(&self) -> Result<()> {
+        let adapter = UsbAdapter::new();
+        adapter.start()?;
+        for device in adapter.peripherals()?.into_iter().filter(|d| self.options.usb_filter(&d.into())) {
+            admit(&self.options, Peripheral::new_usb(device), &self.announcer, &self.peripherals).await;
+        }
+        *self.usb_adapter.lock().await = Some(adapter);
+        self.adapter_states.report(ConnectionType::USB, AdapterState::Available, &self.announcer);
+        Ok(())
+    }
+
+    /// 启动蓝牙适配器，设置蓝牙连接的事件监听器
+    async fn start_ble(&self) -> Result<()> {
+        let ble_adapter = BleAdapter::new();
+        ble_adapter.start_conn_watcher().await?;
+        *self.ble_adapter.lock().await = Some(ble_adapter);
+        self.adapter_states.report(ConnectionType::BLE, AdapterState::Available, &self.announcer);
+        Ok(())
+    }
 
+    /// 尽力模式下记录适配器不可用，否则返回错误
+    fn adapter_failed(&self, conn_type: ConnectionType, err: anyhow::Error) -> Result<()> {
+        if !self.options.best_effort {
+            return Err(err);
+        }
+        self.adapter_states.report(conn_type, AdapterState::Unavailable(err.to_string()), &self.announcer);
         Ok(())
     }
 
+    /// 适配器的最新状态，未启用的连接类型返回 None
+    pub fn adapter_state(&self, conn_type: ConnectionType) -> Option<AdapterState> {
+        self.adapter_states.get(conn_type)
+    }
+
     /// 是否支持广播，由 start 参数options 决定
     pub fn is_support_broadcast(&self) -> bool {
         self.options.is_broadcast
     }
 
+    /// 获取设备事件流，可按设备类型、连接类型、VID/PID 过滤
+    pub fn events(&self) -> EventStream{
+        EventStream::new(self.announcer.subscribe(), Arc::clone(&self.peripherals))
+    }
+
+    /// 获取设备事件流，先回放各适配器当前状态的 AdapterStateChanged
+    /// 和当前所有设备的 DeviceAdd，再接收之后的事件，两者之间不会遗漏
+    pub fn watch(&self) -> EventStream{
+        let (states, (peripherals, receiver)) = self.adapter_states
+            .subscribe(|| self.peripherals.subscribe(&self.announcer));
+        EventStream::new(receiver, Arc::clone(&self.peripherals))
+            .replay_adapter_states(states)
+            .replay(peripherals)
+    }
+
+    /// 注册设备处理函数，匹配的设备加入时（包括已加入的设备）以新任务调用 handler，
+    /// 设备移除时取消传入的 token。取消返回的 token 即注销处理函数，并取消所有会话
+    pub fn on_device<F, Fut>(&self, matcher: DeviceMatcher, handler: F) -> CancellationToken
+    where
+        F: Fn(Peripheral, CancellationToken) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let token = CancellationToken::new();
+        spawn_device_handler(self.watch(), Arc::clone(&self.peripherals), matcher, handler, token.clone());
+        token
+    }
+
+    /// 原子地替换过滤条件，并重新检查设备：已加入但不再匹配的设备以 RemoveReason::Filtered 移除，
+    /// 当前已插入/已连接且新匹配的设备在后台任务中认证后加入。
+    /// 认证任务与热插拔共用，同一设备不会重复加入
+    pub async fn set_filters(&self, filters: Filters) -> Result<()> {
+        // 规则已在 Filters::set_rules 时编译检查
+        *self.options.filters.write().unwrap() = filters;
+
+        for peripheral in self.peripherals.list() {
+            if !self.options.filter_identified(&peripheral) {
+                self.peripherals.remove(&peripheral.id(), RemoveReason::Filtered, &self.announcer);
+            }
+        }
+
+        let usb_devices = match self.usb_adapter.lock().await.as_ref() {
+            Some(adapter) => adapter.peripherals()?,
+            None => Vec::new(),
+        };
+        for device in usb_devices.into_iter().filter(|d| self.options.usb_filter(&d.into())) {
+            spawn_admit_usb(&self.admissions, device, Arc::clone(&self.options), self.announcer.clone(), Arc::clone(&self.peripherals));
+        }
+
+        let ble_devices = match self.ble_adapter.lock().await.as_ref() {
+            Some(adapter) => adapter.peripherals().await?,
+            None => Vec::new(),
+        };
+        for device in ble_devices.into_iter().filter(|d| self.options.ble_filter(d)) {
+            if !device.is_connected().await.unwrap_or(false) {
+                continue;
+            }
+            spawn_identify_ble(&self.admissions, device, Arc::clone(&self.options), self.announcer.clone(), Arc::clone(&self.peripherals));
+        }
+        Ok(())
+    }
+
     /// 获取监听设备变动广播
     pub fn register_broadcast(&self) -> Result<Receiver<CoreEvent>>{
-        match &self.announcer {
-            Some(tx) => {
-                return Ok(tx.subscribe())
-            },
-            None => {
-                bail!("无广播器，请设置启动参数 is_broadcast = true");
-            }
+        if !self.options.is_broadcast {
+            bail!("无广播器，请设置启动参数 is_broadcast = true");
         }
+        Ok(self.announcer.subscribe())
+    }
+}
+
+/// 探测、认证通过后加入设备集合并发送 DeviceAdd，认证失败发送 AuthenticationRejected
+async fn admit(options: &AppOptions, peripheral: Peripheral, sender: &Sender<CoreEvent>, registry: &Registry) {
+    peripheral.set_request_timeout(options.request_timeout);
+    peripheral.set_profiles(Arc::clone(&options.profiles));
+    if !options.probe(&peripheral).await {
+        return;
+    }
+    match options.authenticate(&peripheral).await {
+        AuthResult::Accepted => {
+            peripheral.attach(sender.clone());
+            let key = options.logical_key(&peripheral).await;
+            registry.add(peripheral, key, sender);
+        },
+        AuthResult::Rejected(reason) => {
+            let _ = sender.send(CoreEvent::new(peripheral.conn_type(), EventKind::AuthenticationRejected(peripheral.id(), reason)));
+        },
+    }
+}
+
+/// 读取 BLE 设备信息，满足过滤规则后认证加入，读取失败发送 IdentificationFailed
+async fn identify_ble(options: &AppOptions, device: BlePeripheral, sender: &Sender<CoreEvent>, registry: &Registry) {
+    let uniid = ble_uuid(&device.id());
+    match Peripheral::new_ble(device).await {
+        Ok(ble) => {
+            if options.filter_identified(&ble) {
+                admit(options, ble, sender, registry).await;
+            }
+        },
+        Err(e) => {
+            let _ = sender.send(CoreEvent::new(ConnectionType::BLE, EventKind::IdentificationFailed(uniid, e.to_string())));
+        },
     }
 }
 
-async fn usb_event(adapter:Arc<Mutex<Option<UsbAdapter>>>,options: Arc<AppOptions>,sender:Sender<CoreEvent>) -> Result<()>{
+/// 在单独的任务中认证 USB 设备，慢设备不阻塞调用方；同一设备同时只有一个认证任务，已加入的设备跳过
+fn spawn_admit_usb(admissions: &KeyedTasks<String>, device: HidDevice, options: Arc<AppOptions>, sender: Sender<CoreEvent>, registry: Arc<Registry>) {
+    let identity = usb_identity(&UsbDescriptor::from(&device));
+    admissions.spawn_if_absent(identity.clone(), async move {
+        // 在任务中检查，之前的认证任务已结束，结果已写入设备集合
+        if registry.find_by_identity(&identity).is_none() {
+            admit(&options, Peripheral::new_usb(device), &sender, &registry).await;
+        }
+    });
+}
+
+/// 在单独的任务中读取 BLE 设备信息并认证，同一设备同时只有一个，已加入的设备跳过
+fn spawn_identify_ble(admissions: &KeyedTasks<String>, device: BlePeripheral, options: Arc<AppOptions>, sender: Sender<CoreEvent>, registry: Arc<Registry>) {
+    let id = device.id();
+    admissions.spawn_if_absent(ble_identity(&id.0), async move {
+        if registry.get(&ble_uuid(&id)).is_none() {
+            identify_ble(&options, device, &sender, &registry).await;
+        }
+    });
+}
+
+async fn usb_event(adapter:Arc<Mutex<Option<UsbAdapter>>>,options: Arc<AppOptions>,sender:Sender<CoreEvent>,registry:Arc<Registry>,states:Arc<AdapterStates>,admissions:Arc<KeyedTasks<String>>) -> Result<()>{
+    let debouncer = Debouncer::new(options.debounce);
     let read: crossbeam_channel::Receiver<CentralEvent>;
     {
         let adapter = adapter.lock().await;
@@ -227,25 +608,64 @@ async fn usb_event(adapter:Arc<Mutex<Option<UsbAdapter>>>,options: Arc<AppOption
             Ok(v) => {
                 match v {
                     CentralEvent::DeviceAdd(id) => {
-                        let adapter = adapter.lock().await;
-                        let device = adapter.as_ref().ok_or(anyhow!("Usb Adapter is null"))?.peripheral(&id)?;
-                        if options.usb_filter(&device){
-                            sender.send(CoreEvent::DeviceAdd(Peripheral::new_usb(device)))?;
+                        // 只在取设备时持有适配器锁，认证期间不阻塞其他调用；
+                        // 快速插拔时设备可能已拔出，跳过该设备，不中断事件监听
+                        let device = match adapter.lock().await.as_ref().ok_or(anyhow!("Usb Adapter is null"))?.peripheral(&id) {
+                            Ok(device) => device,
+                            Err(e) => {
+                                println!("usb device {:?} not found:{:?}",id,e);
+                                continue;
+                            },
+                        };
+                        let descriptor = UsbDescriptor::from(&device);
+                        if options.usb_filter(&descriptor){
+                            let identity = usb_identity(&descriptor);
+                            if let Some(peripheral) = registry.find_by_identity(&identity) {
+                                // 窗口期内重新插入，沿用原 Peripheral，替换失效的设备句柄；
+                                // 否则为同一设备的其他 HID 接口，丢弃
+                                if debouncer.cancel_remove(&peripheral) {
+                                    peripheral.replace_usb(device);
+                                }
+                                continue;
+                            }
+                            // 慢设备不阻塞其他设备的热插拔事件，同一设备正在认证时的重复加入丢弃
+                            spawn_admit_usb(&admissions, device, Arc::clone(&options), sender.clone(), Arc::clone(&registry));
                         }
                     },
                     CentralEvent::DeviceRemove(device) => {
-                        if options.usb_filter(&device){
-                            sender.send(CoreEvent::DeviceRemove(Peripheral::new_usb(device).id()))?;
+                        // 认证中的设备已拔出，不再加入
+                        let descriptor = UsbDescriptor::from(&device);
+                        admissions.cancel(&usb_identity(&descriptor));
+                        // 过滤条件可能已更新，按已加入的设备判断
+                        let address = descriptor.address();
+                        if let Some(peripheral) = registry.find_by_address(&address) {
+                            peripheral.link_lost();
+                            debouncer.remove(Arc::clone(&registry), peripheral, RemoveReason::Unplugged, sender.clone());
                         }
                     },
                 }
             },
-            Err(err) => println!("Err:{:?}",err),
+            Err(err) => {
+                // 适配器已停止，不再有事件
+                states.report(ConnectionType::USB, AdapterState::Unavailable(err.to_string()), &sender);
+                bail!(err);
+            },
         }
     }
 }
 
-async fn ble_event(adapter:Arc<Mutex<Option<BleAdapter>>>,options: Arc<AppOptions>,sender:Sender<CoreEvent>) -> Result<()>{
+/// 蓝牙无线电状态对应的适配器状态
+fn ble_adapter_state(state: CentralState) -> AdapterState {
+    match state {
+        CentralState::PoweredOn => AdapterState::Available,
+        CentralState::PoweredOff => AdapterState::PoweredOff,
+        CentralState::Unknown => AdapterState::Unavailable("unknown radio state".to_string()),
+    }
+}
+
+async fn ble_event(adapter:Arc<Mutex<Option<BleAdapter>>>,options: Arc<AppOptions>,sender:Sender<CoreEvent>,registry:Arc<Registry>,states:Arc<AdapterStates>,admissions:Arc<KeyedTasks<String>>) -> Result<()>{
+    let reconnector = Reconnector::default();
+    let debouncer = Debouncer::new(options.debounce);
     let mut events: Pin<Box<dyn Stream<Item=BleCentralEvent> + Send>>;
     {
         let adapter = adapter.lock().await;
@@ -255,22 +675,65 @@ async fn ble_event(adapter:Arc<Mutex<Option<BleAdapter>>>,options: Arc<AppOption
     while let Some(event) = events.next().await {
         match event {
             BleCentralEvent::DeviceConnected(id) => {
-                let adapter = adapter.lock().await;
-                let device = adapter.as_ref().ok_or(anyhow!("Usb Adapter is null"))?.peripheral(&id).await?;
+                let uniid = ble_uuid(&id);
+                if let Some(peripheral) = registry.get(&uniid) {
+                    if debouncer.cancel_remove(&peripheral) {
+                        // 窗口期内重新连接，沿用原 Peripheral
+                        match peripheral.connect(uniid).await {
+                            Ok(_) => {
+                                let _ = sender.send(CoreEvent::new(ConnectionType::BLE, EventKind::Reconnected(uniid)));
+                            },
+                            Err(_) => {
+                                registry.remove(&uniid, RemoveReason::Disconnected, &sender);
+                            },
+                        }
+                    } else if peripheral.user_disconnected() {
+                        // 用户主动断开的设备由 connect 重新连接
+                    } else if let Some(reconnect) = &options.reconnect {
+                        // 重连中的设备沿用原 Peripheral
+                        reconnector.resume(&peripheral, reconnect.clone(), Arc::clone(&registry), sender.clone()).await;
+                    }
+                    // 已加入的设备重复连接，丢弃
+                    continue;
+                }
+                // 设备可能已断开，跳过该设备，不中断事件监听
+                let device = {
+                    let adapter = adapter.lock().await;
+                    adapter.as_ref().ok_or(anyhow!("Ble Adapter is null"))?.peripheral(&id).await
+                };
+                let device = match device {
+                    Ok(device) => device,
+                    Err(e) => {
+                        println!("ble device {:?} not found:{:?}",id,e);
+                        continue;
+                    },
+                };
                 if options.ble_filter(&device){
-                    match Peripheral::new_ble(device).await {
-                        Ok(ble) => {
-                            
-                            sender.send(CoreEvent::DeviceAdd(ble))?;
-                        },
-                        Err(e) => { println!("{:?}",e);},
-                    };
+                    spawn_identify_ble(&admissions, device, Arc::clone(&options), sender.clone(), Arc::clone(&registry));
                 }
             }
             BleCentralEvent::DeviceDisconnected(id) => {
-                let mut slice = [0u8; 16];
-                slice[..6].clone_from_slice(&id.0.into_inner());
-                sender.send(CoreEvent::DeviceRemove(Uuid::from_bytes(slice)))?;
+                admissions.cancel(&ble_identity(&id.0));
+                let uniid = ble_uuid(&id);
+                let peripheral = match registry.get(&uniid) {
+                    Some(peripheral) => peripheral,
+                    None => continue,
+                };
+                match DisconnectAction::new(peripheral.user_disconnected(), options.reconnect.is_some()) {
+                    DisconnectAction::Keep => peripheral.link_lost(),
+                    DisconnectAction::Reconnect => {
+                        let reconnect = options.reconnect.clone().unwrap_or_default();
+                        reconnector.start(peripheral, reconnect, Arc::clone(&registry), sender.clone());
+                    },
+                    DisconnectAction::Remove => {
+                        peripheral.link_lost();
+                        debouncer.remove(Arc::clone(&registry), peripheral, RemoveReason::Disconnected, sender.clone());
+                    },
+                }
+            }
+            BleCentralEvent::StateUpdate(state) => {
+                // 蓝牙关闭时已连接的设备会收到 DeviceDisconnected
+                states.report(ConnectionType::BLE, ble_adapter_state(state), &sender);
             }
             // BleCentralEvent::DeviceUpdated(id) => {
             //     // if let Err(e) = set_status(&id, true).await {
@@ -282,5 +745,6 @@ async fn ble_event(adapter:Arc<Mutex<Option<BleAdapter>>>,options: Arc<AppOption
             _ => {}
         }
     }
+    states.report(ConnectionType::BLE, AdapterState::Unavailable("event stream closed".to_string()), &sender);
     Ok(())
 }
\ No newline at end of file
@@ -0,0 +1,121 @@
+use std::{sync::Arc, time::Duration};
+
+use tokio::{sync::broadcast::Sender, time};
+
+use crate::{
+    api::PeripheralApi,
+    enums::{CoreEvent, RemoveReason},
+    peripheral::Peripheral,
+    registry::Registry,
+    tasks::KeyedTasks,
+};
+
+/// 热插拔去抖，设备移除后在窗口期内重新加入则视为没有变化
+///
+/// 设备以 Peripheral::identity 区分：USB 有序列号时为 VID/PID + 序列号，否则为设备路径；
+/// BLE 为蓝牙地址。
+#[derive(Default)]
+pub(crate) struct Debouncer {
+    window: Option<Duration>,
+    /// 等待移除的设备
+    pending: KeyedTasks<String>,
+}
+
+impl Debouncer {
+    pub(crate) fn new(window: Option<Duration>) -> Self {
+        Debouncer {
+            window,
+            pending: KeyedTasks::default(),
+        }
+    }
+
+    /// 移除设备，设置了窗口期时延迟到窗口期结束
+    pub(crate) fn remove(&self, registry: Arc<Registry>, peripheral: Peripheral, reason: RemoveReason, sender: Sender<CoreEvent>) {
+        let id = peripheral.id();
+        self.schedule(peripheral.identity().to_string(), move || registry.remove(&id, reason, &sender));
+    }
+
+    /// 设备在窗口期内重新加入，取消移除，返回是否有等待中的移除
+    pub(crate) fn cancel_remove(&self, peripheral: &Peripheral) -> bool {
+        self.pending.cancel(&peripheral.identity().to_string())
+    }
+
+    /// 窗口期结束后执行 f，未设置窗口期时立即执行，同一设备重复移除时重新计时
+    fn schedule<F>(&self, key: String, f: F)
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        match self.window {
+            Some(window) => self.pending.spawn_replace(key, async move {
+                time::sleep(window).await;
+                f();
+            }),
+            None => f(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    fn counter() -> (Arc<AtomicUsize>, impl Fn() -> Box<dyn FnOnce() + Send>) {
+        let count = Arc::new(AtomicUsize::new(0));
+        let clone = Arc::clone(&count);
+        (count, move || {
+            let count = Arc::clone(&clone);
+            Box::new(move || {
+                count.fetch_add(1, Ordering::SeqCst);
+            })
+        })
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn removed_after_window() {
+        let debouncer = Debouncer::new(Some(Duration::from_millis(100)));
+        let (count, remove) = counter();
+        debouncer.schedule("USB:1".to_string(), remove());
+
+        time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(count.load(Ordering::SeqCst), 0);
+        time::sleep(Duration::from_millis(60)).await;
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+        assert!(!debouncer.pending.cancel(&"USB:1".to_string()));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn readded_within_window() {
+        let debouncer = Debouncer::new(Some(Duration::from_millis(100)));
+        let (count, remove) = counter();
+        debouncer.schedule("USB:1".to_string(), remove());
+
+        time::sleep(Duration::from_millis(50)).await;
+        assert!(debouncer.pending.cancel(&"USB:1".to_string()));
+        time::sleep(Duration::from_millis(200)).await;
+        assert_eq!(count.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn repeated_remove_restarts_window() {
+        let debouncer = Debouncer::new(Some(Duration::from_millis(100)));
+        let (count, remove) = counter();
+        debouncer.schedule("USB:1".to_string(), remove());
+        time::sleep(Duration::from_millis(80)).await;
+        debouncer.schedule("USB:1".to_string(), remove());
+
+        time::sleep(Duration::from_millis(80)).await;
+        assert_eq!(count.load(Ordering::SeqCst), 0);
+        time::sleep(Duration::from_millis(30)).await;
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn no_window() {
+        let debouncer = Debouncer::new(None);
+        let (count, remove) = counter();
+        debouncer.schedule("USB:1".to_string(), remove());
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+    }
+}
@@ -1,9 +1,11 @@
 use thiserror::Error;
-use std::time::Duration;
+use std::{fmt, str::FromStr, time::{Duration, SystemTime}};
 use strum_macros::{EnumString, Display, FromRepr};
 use uuid::Uuid;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Deserializer, Serialize};
 
-use crate:: peripheral::Peripheral;
+use crate::{api::PeripheralApi, peripheral::{Peripheral, PeripheralInfo}};
 
 /// The main error type returned by most methods in btleplug.
 #[derive(Error, Debug)]
@@ -27,7 +29,8 @@ pub enum Error {
     Other(Box<dyn std::error::Error + Send + Sync>),
 }
 
-#[derive(Debug, Clone, Copy, Eq, PartialEq, EnumString, Display, FromRepr)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, EnumString, Display, FromRepr)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[repr(u8)]
 pub enum ConnectionType{
     /// USB 类型连接
@@ -59,25 +62,57 @@ impl From<u8> for ConnectionType {
     }
 }
 
-/// 具体设备类型
-#[derive(Debug, Clone, Eq, PartialEq, EnumString, Display, FromRepr)]
-#[repr(u16)]
+/// 设备连接状态
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Display)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum PeripheralState {
+    /// 已连接，可读写
+    Connected,
+    /// 已断开，调用 connect 重新连接
+    Disconnected,
+}
+
+/// 具体设备类型，编号见 `code`
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum DeviceType {
-    #[strum(serialize = "Keyboard")]
-    Keyboard = 1,
-    #[strum(serialize = "Mouse")]
-    Mouse = 2,
-    #[strum(serialize = "Touchpad")]
-    Touchpad = 3,
+    Keyboard,
+    Mouse,
+    Touchpad,
     /// 复合 键盘触摸板
-    #[strum(serialize = "MulKeyboardTouchpad")]
-    MulKeyboardTouchpad = 4,
-    #[strum(serialize = "Other")]
-    Other = 100,
+    MulKeyboardTouchpad,
+    Other,
+    /// 未识别的类型编号
+    Unknown(u16),
+}
+
+impl DeviceType {
+    /// 按设备上报的编号识别，未知编号保留为 Unknown
+    pub fn from_code(code: u16) -> Self {
+        match code {
+            1 => Self::Keyboard,
+            2 => Self::Mouse,
+            3 => Self::Touchpad,
+            4 => Self::MulKeyboardTouchpad,
+            100 => Self::Other,
+            _ => Self::Unknown(code),
+        }
+    }
+
+    pub fn code(&self) -> u16 {
+        match *self {
+            Self::Keyboard => 1,
+            Self::Mouse => 2,
+            Self::Touchpad => 3,
+            Self::MulKeyboardTouchpad => 4,
+            Self::Other => 100,
+            Self::Unknown(code) => code,
+        }
+    }
 }
 
 pub fn device_type_from_repr(d: u16) -> DeviceType {
-    DeviceType::from_repr(d).unwrap_or_default()
+    DeviceType::from_code(d)
 }
 
 impl Default for DeviceType {
@@ -86,64 +121,445 @@ impl Default for DeviceType {
     }
 }
 
-/// 具体芯片型号，TODO: 不同的芯片厂商，采用不同的分类
-#[derive(Debug, Clone, Copy, Eq, PartialEq, EnumString, Display )]
-pub enum ChipType {
-    #[strum(serialize = "AC632N")]
+impl fmt::Display for DeviceType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Keyboard => f.write_str("Keyboard"),
+            Self::Mouse => f.write_str("Mouse"),
+            Self::Touchpad => f.write_str("Touchpad"),
+            Self::MulKeyboardTouchpad => f.write_str("MulKeyboardTouchpad"),
+            Self::Other => f.write_str("Other"),
+            Self::Unknown(code) => write!(f, "Unknown({})", code),
+        }
+    }
+}
+
+impl FromStr for DeviceType {
+    type Err = strum::ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "Keyboard" => Ok(Self::Keyboard),
+            "Mouse" => Ok(Self::Mouse),
+            "Touchpad" => Ok(Self::Touchpad),
+            "MulKeyboardTouchpad" => Ok(Self::MulKeyboardTouchpad),
+            "Other" => Ok(Self::Other),
+            s => parse_unknown(s)
+                .map(Self::Unknown)
+                .ok_or(strum::ParseError::VariantNotFound),
+        }
+    }
+}
+
+/// 未内置的芯片型号、厂商名称的前缀，如 `custom:BK3633`
+pub const CUSTOM_PREFIX: &str = "custom:";
+
+/// 解析 `Unknown(编号)`
+fn parse_unknown(s: &str) -> Option<u16> {
+    s.strip_prefix("Unknown(")
+        .and_then(|code| code.strip_suffix(')'))
+        .and_then(|code| code.parse().ok())
+}
+
+/// 解析带 `custom:` 前缀的名称，名称不能为空
+fn parse_custom(s: &str) -> Option<&str> {
+    s.strip_prefix(CUSTOM_PREFIX)
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+}
+
+/// 杰理芯片型号
+#[derive(Debug, Clone, Copy, Eq, PartialEq, EnumString, Display)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum JlChip {
     AC632N,
-    #[strum(serialize = "AC635N")]
     AC635N,
-    #[strum(serialize = "PAR2860")]
+}
+
+/// 原相芯片型号
+#[derive(Debug, Clone, Copy, Eq, PartialEq, EnumString, Display)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum ParChip {
     PAR2860,
 }
 
+/// 具体芯片型号，按芯片厂商分类
+///
+/// 未内置的型号写作 `custom:名称`，解析为 Custom，其余未知名称解析失败；
+/// 未识别的设备为 Unknown。
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum ChipType {
+    JL(JlChip),
+    PAR(ParChip),
+    /// 未内置的芯片型号
+    Custom(String),
+    /// 未识别
+    Unknown,
+}
+
+impl ChipType {
+    pub const AC632N: ChipType = ChipType::JL(JlChip::AC632N);
+    pub const AC635N: ChipType = ChipType::JL(JlChip::AC635N);
+    pub const PAR2860: ChipType = ChipType::PAR(ParChip::PAR2860);
+
+    /// 内置型号所属厂商
+    pub fn manufacturer(&self) -> Option<ChipManufacturer> {
+        match self {
+            Self::JL(_) => Some(ChipManufacturer::JL),
+            Self::PAR(_) => Some(ChipManufacturer::PAR),
+            Self::Custom(_) | Self::Unknown => None,
+        }
+    }
+}
+
 impl Default for ChipType {
     fn default() -> Self {
-        Self::PAR2860
+        Self::Unknown
+    }
+}
+
+impl fmt::Display for ChipType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::JL(chip) => chip.fmt(f),
+            Self::PAR(chip) => chip.fmt(f),
+            Self::Custom(name) => write!(f, "{}{}", CUSTOM_PREFIX, name),
+            Self::Unknown => f.write_str("Unknown"),
+        }
+    }
+}
+
+impl FromStr for ChipType {
+    type Err = strum::ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s == "Unknown" {
+            return Ok(Self::Unknown);
+        }
+        if let Some(name) = parse_custom(s) {
+            return Ok(Self::Custom(name.to_string()));
+        }
+        JlChip::from_str(s).map(Self::JL)
+            .or_else(|_| ParChip::from_str(s).map(Self::PAR))
     }
 }
 
 /// 芯片制造厂商
-#[derive(Debug, Clone, Copy, Eq, PartialEq, EnumString, Display, FromRepr)]
-#[repr(u16)]
+///
+/// 内置厂商之外的写作 `custom:名称`，解析为 Custom；未知的厂商编号保留为 `Unknown(Some(code))`，
+/// 未识别的设备为 `Unknown(None)`。
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum ChipManufacturer {
     /// 杰理
-    #[strum(serialize = "JL")]
     JL,
     /// 原相
-    #[strum(serialize = "PAR")]
     PAR,
+    /// 未内置的厂商
+    Custom(String),
+    /// 未识别，Some 为设备上报的未知编号
+    Unknown(Option<u16>),
 }
 
 impl Default for ChipManufacturer {
     fn default() -> Self {
-        Self::PAR
+        Self::Unknown(None)
     }
 }
 
 impl ChipManufacturer {
-    fn from_u8(v: u16) -> Self {
+    /// 按厂商编号识别，未知编号保留为 Unknown
+    pub fn from_code(v: u16) -> Self {
         match v {
             0 => Self::JL,
             1 => Self::PAR,
-            _ => Self::PAR,
+            _ => Self::Unknown(Some(v)),
         }
     }
 
-    pub fn num(&self) -> u16 {
+    /// 厂商编号，未内置的厂商和未识别的设备返回 None
+    pub fn code(&self) -> Option<u16> {
         match *self {
-            Self::JL => 0 ,
-            Self::PAR => 1 ,
+            Self::JL => Some(0),
+            Self::PAR => Some(1),
+            Self::Unknown(code) => code,
+            Self::Custom(_) => None,
         }
     }
 
+    /// 厂商编号，没有编号时返回 u16::MAX
+    #[deprecated(note = "use `code()`, which returns None for manufacturers without a code")]
+    pub fn num(&self) -> u16 {
+        self.code().unwrap_or(u16::MAX)
+    }
+}
+
+impl fmt::Display for ChipManufacturer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::JL => f.write_str("JL"),
+            Self::PAR => f.write_str("PAR"),
+            Self::Custom(name) => write!(f, "{}{}", CUSTOM_PREFIX, name),
+            Self::Unknown(None) => f.write_str("Unknown"),
+            Self::Unknown(Some(code)) => write!(f, "Unknown({})", code),
+        }
+    }
+}
+
+impl FromStr for ChipManufacturer {
+    type Err = strum::ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "JL" => Ok(Self::JL),
+            "PAR" => Ok(Self::PAR),
+            "Unknown" => Ok(Self::Unknown(None)),
+            s => match parse_custom(s) {
+                Some(name) => Ok(Self::Custom(name.to_string())),
+                None => parse_unknown(s)
+                    .map(|code| Self::Unknown(Some(code)))
+                    .ok_or(strum::ParseError::VariantNotFound),
+            },
+        }
+    }
+}
+
+/// 设备信息变化的字段
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct InfoChange {
+    /// 字段名，与 PeripheralApi 的访问方法同名
+    pub field: &'static str,
+    pub old: String,
+    pub new: String,
+}
+
+/// InfoChange 可能的字段名
+#[cfg(feature = "serde")]
+const INFO_FIELDS: [&str; 11] = [
+    "vid", "pid", "chip_manufacturer", "device_type", "device_name", "chip_type",
+    "software_version", "hardware_version", "firmware_version", "serial_number", "manufacturer",
+];
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for InfoChange {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        struct Raw {
+            field: String,
+            old: String,
+            new: String,
+        }
+        let raw = Raw::deserialize(deserializer)?;
+        let field = INFO_FIELDS.iter().find(|f| **f == raw.field)
+            .ok_or_else(|| serde::de::Error::unknown_variant(&raw.field, &INFO_FIELDS))?;
+        Ok(InfoChange { field, old: raw.old, new: raw.new })
+    }
+}
+
+/// 设备移除原因
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Display)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum RemoveReason {
+    /// USB 拔出
+    Unplugged,
+    /// BLE 连接断开（未开启自动重连）
+    Disconnected,
+    /// BLE 自动重连次数用尽
+    ReconnectFailed,
+    /// 过滤条件更新后不再匹配
+    Filtered,
+}
+
+/// 适配器状态
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum AdapterState {
+    /// 适配器可用
+    Available,
+    /// 蓝牙已关闭
+    PoweredOff,
+    /// 适配器不可用，附带原因
+    Unavailable(String),
+}
+
+/// 事件，包含发生时间和连接类型
+#[derive(Debug, Clone)]
+pub struct CoreEvent {
+    /// 事件发生时间
+    pub timestamp: SystemTime,
+    /// 事件来源的连接类型，Resync 为 None
+    pub conn_type: Option<ConnectionType>,
+    /// 事件内容
+    pub kind: EventKind,
+}
+
+impl CoreEvent {
+    pub fn new(conn_type: ConnectionType, kind: EventKind) -> Self {
+        CoreEvent {
+            timestamp: SystemTime::now(),
+            conn_type: Some(conn_type),
+            kind,
+        }
+    }
+
+    /// 事件流落后，丢失了 missed 个事件
+    pub fn resync(missed: u64) -> Self {
+        CoreEvent {
+            timestamp: SystemTime::now(),
+            conn_type: None,
+            kind: EventKind::Resync(missed),
+        }
+    }
+
+    /// 可序列化的事件记录
+    pub fn record(&self) -> EventRecord {
+        EventRecord::from(self)
+    }
+
+    /// 事件相关的设备 id，适配器事件返回 None
+    pub fn device_id(&self) -> Option<Uuid> {
+        match &self.kind {
+            EventKind::DeviceAdd(p) => Some(p.id()),
+            EventKind::DeviceRemove(info, _) => Some(info.id),
+            EventKind::DeviceUpdated(id, _)
+            | EventKind::IdentificationFailed(id, _)
+            | EventKind::AuthenticationRejected(id, _)
+            | EventKind::ConnectionLost(id)
+            | EventKind::Reconnecting(id, _)
+            | EventKind::Reconnected(id)
+            | EventKind::Notification(id, _) => Some(*id),
+            EventKind::AdapterStateChanged(_) | EventKind::Resync(_) => None,
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
-pub enum CoreEvent
+pub enum EventKind
 {
     /// 设备连接
     DeviceAdd(Peripheral),
-    /// 设备断开
-    DeviceRemove(Uuid),
-}
\ No newline at end of file
+    /// 设备断开，附带移除前最后的设备信息
+    DeviceRemove(PeripheralInfo, RemoveReason),
+    /// 设备信息刷新后有变化
+    DeviceUpdated(Uuid, Vec<InfoChange>),
+    /// 适配器状态变化（蓝牙开关、USB 适配器故障）
+    AdapterStateChanged(AdapterState),
+    /// 设备通过过滤但无法识别（缺少服务、读取设备信息失败）
+    IdentificationFailed(Uuid, String),
+    /// 设备身份认证失败，未加入
+    AuthenticationRejected(Uuid, String),
+    /// 连接意外断开，开启自动重连时发送，随后发送 Reconnecting
+    ConnectionLost(Uuid),
+    /// BLE 连接断开，正在进行第 n 次重连
+    Reconnecting(Uuid, u32),
+    /// BLE 重连成功（连接恢复），原 Peripheral 继续可用
+    Reconnected(Uuid),
+    /// BLE 设备主动上报的 notify 数据（包含 request 的应答）。
+    /// USB 设备的输入报告由 read/request 读取，不产生此事件
+    Notification(Uuid, Vec<u8>),
+    /// 事件流落后丢失了 n 个事件，需要调用 App::peripherals 重新同步设备列表
+    Resync(u64),
+}
+
+/// 可序列化的事件记录，DeviceAdd 中的设备对象替换为信息快照，用于持久化或跨进程发送
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct EventRecord {
+    pub timestamp: SystemTime,
+    pub conn_type: Option<ConnectionType>,
+    pub kind: EventRecordKind,
+}
+
+/// 与 EventKind 一一对应，不包含设备对象
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum EventRecordKind {
+    DeviceAdd(PeripheralInfo),
+    DeviceRemove(PeripheralInfo, RemoveReason),
+    DeviceUpdated(Uuid, Vec<InfoChange>),
+    AdapterStateChanged(AdapterState),
+    IdentificationFailed(Uuid, String),
+    AuthenticationRejected(Uuid, String),
+    ConnectionLost(Uuid),
+    Reconnecting(Uuid, u32),
+    Reconnected(Uuid),
+    Notification(Uuid, Vec<u8>),
+    Resync(u64),
+}
+
+impl From<&CoreEvent> for EventRecord {
+    fn from(event: &CoreEvent) -> Self {
+        let kind = match &event.kind {
+            EventKind::DeviceAdd(p) => EventRecordKind::DeviceAdd(p.info()),
+            EventKind::DeviceRemove(info, reason) => EventRecordKind::DeviceRemove(info.clone(), *reason),
+            EventKind::DeviceUpdated(id, changes) => EventRecordKind::DeviceUpdated(*id, changes.clone()),
+            EventKind::AdapterStateChanged(state) => EventRecordKind::AdapterStateChanged(state.clone()),
+            EventKind::IdentificationFailed(id, e) => EventRecordKind::IdentificationFailed(*id, e.clone()),
+            EventKind::AuthenticationRejected(id, e) => EventRecordKind::AuthenticationRejected(*id, e.clone()),
+            EventKind::ConnectionLost(id) => EventRecordKind::ConnectionLost(*id),
+            EventKind::Reconnecting(id, n) => EventRecordKind::Reconnecting(*id, *n),
+            EventKind::Reconnected(id) => EventRecordKind::Reconnected(*id),
+            EventKind::Notification(id, data) => EventRecordKind::Notification(*id, data.clone()),
+            EventKind::Resync(n) => EventRecordKind::Resync(*n),
+        };
+        EventRecord {
+            timestamp: event.timestamp,
+            conn_type: event.conn_type,
+            kind,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unknown_is_kept() {
+        assert_eq!(DeviceType::from_code(7), DeviceType::Unknown(7));
+        assert_eq!(DeviceType::from_code(7).code(), 7);
+        assert_eq!(ChipManufacturer::from_code(9), ChipManufacturer::Unknown(Some(9)));
+        assert_eq!(ChipManufacturer::from_code(9).code(), Some(9));
+        assert_eq!(ChipManufacturer::from_code(1), ChipManufacturer::PAR);
+        assert_eq!(ChipType::from_str("AC635N").unwrap(), ChipType::AC635N);
+        assert_eq!(ChipType::from_str("custom:BK3633").unwrap(), ChipType::Custom("BK3633".to_string()));
+        assert_eq!(ChipType::PAR2860.manufacturer(), Some(ChipManufacturer::PAR));
+        assert_eq!(ChipType::default().to_string(), "Unknown");
+    }
+
+    #[test]
+    fn unknown_name_is_error() {
+        assert!(ChipType::from_str("").is_err());
+        assert!(ChipType::from_str("BK3633").is_err());
+        assert!(ChipType::from_str("AC365N").is_err());
+        assert!(ChipType::from_str("custom:").is_err());
+        assert!(ChipManufacturer::from_str("Jl").is_err());
+        assert!(ChipManufacturer::from_str("custom: ").is_err());
+        assert!(DeviceType::from_str("Unknown(x)").is_err());
+    }
+
+    #[test]
+    fn display_roundtrip() {
+        for v in [DeviceType::Keyboard, DeviceType::Other, DeviceType::Unknown(7)] {
+            assert_eq!(DeviceType::from_str(&v.to_string()).unwrap(), v);
+        }
+        for v in [ChipType::AC632N, ChipType::PAR2860, ChipType::Custom("BK3633".to_string()), ChipType::Unknown] {
+            assert_eq!(ChipType::from_str(&v.to_string()).unwrap(), v);
+        }
+        for v in [ChipManufacturer::JL, ChipManufacturer::Custom("Beken".to_string()), ChipManufacturer::Unknown(None), ChipManufacturer::Unknown(Some(9))] {
+            assert_eq!(ChipManufacturer::from_str(&v.to_string()).unwrap(), v);
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn info_change_roundtrip() {
+        let change = InfoChange { field: "firmware_version", old: "1.0".to_string(), new: "1.1".to_string() };
+        let json = serde_json::to_string(&change).unwrap();
+        assert_eq!(serde_json::from_str::<InfoChange>(&json).unwrap(), change);
+        assert!(serde_json::from_str::<InfoChange>(r#"{"field":"x","old":"","new":""}"#).is_err());
+    }
+}
@@ -0,0 +1,301 @@
+use anyhow::{Result, anyhow};
+use btleplug::api::Peripheral as _;
+use regex::Regex;
+use uuid::Uuid;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use btleplug::winrtble::peripheral::Peripheral as BlePeripheral;
+
+use crate::{
+    api::PeripheralApi,
+    core::{BleFilterHandler, UsbFilterHandler},
+    peripheral::{Device, Peripheral, UsbDescriptor},
+};
+
+/// VID/PID 匹配范围，可写为单个值或 `{ min, max }`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(untagged))]
+pub enum IdRange {
+    Exact(u16),
+    Range { min: u16, max: u16 },
+}
+
+impl IdRange {
+    pub fn contains(&self, v: u16) -> bool {
+        match *self {
+            IdRange::Exact(e) => v == e,
+            IdRange::Range { min, max } => (min..=max).contains(&v),
+        }
+    }
+}
+
+/// 声明式设备过滤规则，可组合、可序列化
+///
+/// 不适用于当前连接类型的条件（如 USB 设备的 GATT 服务）视为不匹配。
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(rename_all = "snake_case"))]
+pub enum FilterRule {
+    /// 全部满足
+    All(Vec<FilterRule>),
+    /// 任一满足
+    Any(Vec<FilterRule>),
+    /// 取反
+    Not(Box<FilterRule>),
+    VendorId(IdRange),
+    ProductId(IdRange),
+    /// HID usage page，仅 USB
+    UsagePage(u16),
+    /// HID usage，仅 USB
+    Usage(u16),
+    /// 输入报告长度，仅 USB
+    InputReportLength(u16),
+    /// 设备名称正则
+    NameRegex(String),
+    /// 需要的 GATT 服务，仅 BLE
+    Service(Uuid),
+    /// 需要的 GATT 特征，仅 BLE
+    Characteristic(Uuid),
+    /// 厂商名称，USB 为 manufacturer string，BLE 为 Manufacturer Name String
+    Manufacturer(String),
+}
+
+/// USB 和 BLE 设备的过滤规则，未设置的连接类型不过滤
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(default))]
+pub struct FilterRules {
+    pub usb: Option<FilterRule>,
+    pub ble: Option<FilterRule>,
+}
+
+impl FilterRules {
+    /// 检查规则，返回第一个错误（如正则格式错误）
+    pub fn validate(&self) -> Result<()> {
+        self.compile().map(|_| ())
+    }
+
+    /// 编译规则，设置规则时调用一次，之后匹配时复用
+    pub(crate) fn compile(&self) -> Result<CompiledRules> {
+        Ok(CompiledRules {
+            usb: self.usb.as_ref().map(FilterRule::compile).transpose()?,
+            ble: self.ble.as_ref().map(FilterRule::compile).transpose()?,
+        })
+    }
+}
+
+/// 预编译的过滤规则
+#[derive(Debug, Clone, Default)]
+pub(crate) struct CompiledRules {
+    usb: Option<CompiledRule>,
+    ble: Option<CompiledRule>,
+}
+
+impl CompiledRules {
+    /// USB 设备加入前，按 HID 描述信息过滤
+    pub(crate) fn matches_usb(&self, device: &UsbDescriptor) -> bool {
+        self.usb.as_ref().map_or(true, |r| r.matches(&FilterContext::from_usb(device)))
+    }
+
+    /// 按已识别的设备过滤，BLE 设备在读取设备信息之后过滤
+    pub(crate) fn matches(&self, peripheral: &Peripheral) -> bool {
+        let rule = match peripheral.device() {
+            Device::Usb(_) => &self.usb,
+            Device::Ble(_) => &self.ble,
+        };
+        rule.as_ref().map_or(true, |r| r.matches(&FilterContext::from_peripheral(peripheral)))
+    }
+}
+
+/// 设备过滤条件，过滤函数与声明式规则同时满足才加入，可通过 `App::set_filters` 运行时替换
+#[derive(Default)]
+pub struct Filters {
+    pub(crate) usb: Option<UsbFilterHandler>,
+    pub(crate) ble: Option<BleFilterHandler>,
+    pub(crate) rules: CompiledRules,
+}
+
+impl Filters {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_usb_filter(mut self, filter_handler: UsbFilterHandler) -> Self {
+        self.usb = Some(filter_handler);
+        self
+    }
+
+    pub fn set_ble_filter(mut self, filter_handler: BleFilterHandler) -> Self {
+        self.ble = Some(filter_handler);
+        self
+    }
+
+    /// 设置声明式规则，规则格式错误时返回错误
+    pub fn set_rules(mut self, rules: FilterRules) -> Result<Self> {
+        self.rules = rules.compile()?;
+        Ok(self)
+    }
+
+    /// USB 设备加入前过滤
+    pub(crate) fn matches_usb(&self, device: &UsbDescriptor) -> bool {
+        self.usb.as_ref().map_or(true, |f| f(device)) && self.rules.matches_usb(device)
+    }
+
+    /// BLE 设备读取设备信息前过滤，只执行过滤函数
+    pub(crate) fn matches_ble(&self, device: &BlePeripheral) -> bool {
+        self.ble.as_ref().map_or(true, |f| f(device))
+    }
+
+    /// 按已识别的设备过滤
+    pub(crate) fn matches(&self, peripheral: &Peripheral) -> bool {
+        match peripheral.device() {
+            Device::Usb(device) => self.matches_usb(&device),
+            Device::Ble(device) => self.matches_ble(&device) && self.rules.matches(peripheral),
+        }
+    }
+}
+
+/// 规则匹配使用的设备属性
+#[derive(Debug, Default)]
+pub(crate) struct FilterContext {
+    vid: u16,
+    pid: u16,
+    usage_page: Option<u16>,
+    usage: Option<u16>,
+    input_report_length: Option<u16>,
+    name: String,
+    manufacturer: String,
+    services: Vec<Uuid>,
+    characteristics: Vec<Uuid>,
+}
+
+impl FilterContext {
+    pub(crate) fn from_usb(device: &UsbDescriptor) -> Self {
+        FilterContext {
+            vid: device.vendor_id,
+            pid: device.product_id,
+            usage_page: Some(device.usage_page),
+            usage: Some(device.usage),
+            input_report_length: Some(device.input_report_byte_length),
+            name: device.product_string.clone(),
+            manufacturer: device.manufacturer_string.clone(),
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn from_peripheral(peripheral: &Peripheral) -> Self {
+        let info = peripheral.info();
+        match peripheral.device() {
+            Device::Usb(device) => FilterContext::from_usb(&device),
+            Device::Ble(device) => {
+                let characteristics = device.characteristics();
+                FilterContext {
+                    vid: info.vid,
+                    pid: info.pid,
+                    // 按设备上报的名称匹配，与加入前的检查一致，不受型号表显示名称影响
+                    name: info.reported_name,
+                    manufacturer: info.manufacturer,
+                    services: characteristics.iter().map(|c| c.service_uuid).collect(),
+                    characteristics: characteristics.iter().map(|c| c.uuid).collect(),
+                    ..Default::default()
+                }
+            },
+        }
+    }
+}
+
+impl FilterRule {
+    /// 检查规则，正则格式错误时返回错误
+    pub fn validate(&self) -> Result<()> {
+        self.compile().map(|_| ())
+    }
+
+    /// 编译规则，正则只在这里编译一次
+    pub(crate) fn compile(&self) -> Result<CompiledRule> {
+        Ok(match self {
+            FilterRule::All(rules) => CompiledRule::All(rules.iter().map(FilterRule::compile).collect::<Result<_>>()?),
+            FilterRule::Any(rules) => CompiledRule::Any(rules.iter().map(FilterRule::compile).collect::<Result<_>>()?),
+            FilterRule::Not(rule) => CompiledRule::Not(Box::new(rule.compile()?)),
+            FilterRule::NameRegex(re) => CompiledRule::NameRegex(Regex::new(re).map_err(|e| anyhow!("name_regex {:?}: {}", re, e))?),
+            rule => CompiledRule::Plain(rule.clone()),
+        })
+    }
+
+    /// 匹配单个条件，组合条件和正则由 CompiledRule 处理
+    fn matches_plain(&self, ctx: &FilterContext) -> bool {
+        match self {
+            FilterRule::VendorId(range) => range.contains(ctx.vid),
+            FilterRule::ProductId(range) => range.contains(ctx.pid),
+            FilterRule::UsagePage(v) => ctx.usage_page == Some(*v),
+            FilterRule::Usage(v) => ctx.usage == Some(*v),
+            FilterRule::InputReportLength(v) => ctx.input_report_length == Some(*v),
+            FilterRule::Service(uuid) => ctx.services.contains(uuid),
+            FilterRule::Characteristic(uuid) => ctx.characteristics.contains(uuid),
+            FilterRule::Manufacturer(m) => ctx.manufacturer == *m,
+            FilterRule::All(_) | FilterRule::Any(_) | FilterRule::Not(_) | FilterRule::NameRegex(_) => false,
+        }
+    }
+}
+
+/// 编译后的过滤规则，正则已编译
+#[derive(Debug, Clone)]
+pub(crate) enum CompiledRule {
+    All(Vec<CompiledRule>),
+    Any(Vec<CompiledRule>),
+    Not(Box<CompiledRule>),
+    NameRegex(Regex),
+    /// 不需要编译的单个条件
+    Plain(FilterRule),
+}
+
+impl CompiledRule {
+    pub(crate) fn matches(&self, ctx: &FilterContext) -> bool {
+        match self {
+            CompiledRule::All(rules) => rules.iter().all(|r| r.matches(ctx)),
+            CompiledRule::Any(rules) => rules.iter().any(|r| r.matches(ctx)),
+            CompiledRule::Not(rule) => !rule.matches(ctx),
+            CompiledRule::NameRegex(re) => re.is_match(&ctx.name),
+            CompiledRule::Plain(rule) => rule.matches_plain(ctx),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keyboard() -> FilterContext {
+        FilterContext {
+            vid: 0x3373,
+            pid: 0x0010,
+            usage_page: Some(0xFF00),
+            input_report_length: Some(65),
+            name: "Keyboard K1".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn compose() {
+        let rule = FilterRule::All(vec![
+            FilterRule::VendorId(IdRange::Exact(0x3373)),
+            FilterRule::ProductId(IdRange::Range { min: 0x0001, max: 0x00FF }),
+            FilterRule::Any(vec![
+                FilterRule::InputReportLength(65),
+                FilterRule::Service(Uuid::nil()),
+            ]),
+            FilterRule::Not(Box::new(FilterRule::NameRegex("^Mouse".to_string()))),
+        ]);
+        assert!(rule.compile().unwrap().matches(&keyboard()));
+        assert!(!FilterRule::UsagePage(0x0001).compile().unwrap().matches(&keyboard()));
+        assert!(!FilterRule::Characteristic(Uuid::nil()).compile().unwrap().matches(&keyboard()));
+    }
+
+    #[test]
+    fn validate_regex() {
+        assert!(FilterRule::NameRegex("(".to_string()).validate().is_err());
+        assert!(FilterRule::Not(Box::new(FilterRule::NameRegex("K\\d".to_string()))).validate().is_ok());
+        // 正则格式错误的规则无法设置，不会在匹配时静默不匹配
+        let rules = FilterRules { usb: Some(FilterRule::NameRegex("(".to_string())), ble: None };
+        assert!(Filters::new().set_rules(rules).is_err());
+    }
+}
@@ -0,0 +1,75 @@
+use std::{collections::HashMap, future::Future, sync::Arc};
+
+use futures::stream::StreamExt;
+use tokio_util::sync::CancellationToken;
+use uuid::Uuid;
+
+use crate::{
+    api::PeripheralApi,
+    enums::EventKind,
+    matcher::DeviceMatcher,
+    peripheral::Peripheral,
+    registry::Registry,
+    stream::EventStream,
+};
+
+/// 分发设备事件，匹配的设备加入时启动 handler，设备移除或 token 取消时取消会话
+pub(crate) fn spawn_device_handler<F, Fut>(
+    events: EventStream,
+    registry: Arc<Registry>,
+    matcher: DeviceMatcher,
+    handler: F,
+    token: CancellationToken,
+)
+where
+    F: Fn(Peripheral, CancellationToken) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    let mut events = events.matching(matcher.clone());
+    tokio::spawn(async move {
+        let mut sessions: HashMap<Uuid, CancellationToken> = HashMap::new();
+        let start = |sessions: &mut HashMap<Uuid, CancellationToken>, peripheral: Peripheral| {
+            let session = token.child_token();
+            if let Some(old) = sessions.insert(peripheral.id(), session.clone()) {
+                old.cancel();
+            }
+            tokio::spawn(handler(peripheral, session));
+        };
+        loop {
+            let event = tokio::select! {
+                _ = token.cancelled() => break,
+                event = events.next() => match event {
+                    Some(event) => event,
+                    None => break,
+                },
+            };
+            match event.kind {
+                EventKind::DeviceAdd(peripheral) => start(&mut sessions, peripheral),
+                EventKind::DeviceRemove(info, _) => {
+                    if let Some(session) = sessions.remove(&info.id) {
+                        session.cancel();
+                    }
+                },
+                EventKind::Resync(_) => {
+                    // 丢失了事件，按设备集合重新对齐会话
+                    sessions.retain(|id, session| {
+                        let alive = registry.get(id).is_some();
+                        if !alive {
+                            session.cancel();
+                        }
+                        alive
+                    });
+                    for peripheral in registry.list() {
+                        if matcher.matches(&peripheral.info()) && !sessions.contains_key(&peripheral.id()) {
+                            start(&mut sessions, peripheral);
+                        }
+                    }
+                },
+                _ => {},
+            }
+        }
+        for session in sessions.values() {
+            session.cancel();
+        }
+    });
+}
@@ -1,16 +1,17 @@
 // 
 
 //! ### For example
-//! ```rust
+//! ```rust,no_run
 //! #[tokio::main]
 //! async fn main() -> Result<(), Box<dyn std::error::Error>> {
 //!     use peripheral_manager::{
 //!         core::{AppOptions,App},
-//!         api::{CoreEvent,PeripheralApi},
+//!         api::PeripheralApi,
+//!         enums::EventKind,
 //!     };
 //! 
 //!     let options = AppOptions::new().set_broadcast(true, 10).
-//!     set_usb_filter(Box::new(|x| x.vendor_id == 0x3373 && && x.input_report_byte_length == 65));
+//!     set_usb_filter(Box::new(|x| x.vendor_id == 0x3373 && x.input_report_byte_length == 65));
 //! 
 //!     let app = App::start(Some(options)).await.unwrap();
 //! 
@@ -22,11 +23,11 @@
 //!     loop {
 //!         match channl.recv().await {
 //!             Ok(v) => {
-//!                 match v {
-//!                     CoreEvent::DeviceAdd(id) => {
-//!                         println!("add device:{}",id);
+//!                 match v.kind {
+//!                     EventKind::DeviceAdd(id) => {
+//!                         println!("add device:{}",id.id());
 //! 
-//!                         let device = app.peripheral(&id).await.unwrap();
+//!                         let device = app.peripheral(&id.id()).await.unwrap();
 //!                         
 //!                         println!("device path :{:?}",device.address());
 //!                         let mut buffer = [0; 51];
@@ -37,9 +38,10 @@
 //!                         device.write(&write_buf).await?;
 //!                         println!("over");
 //!                     },
-//!                     CoreEvent::DeviceRemove(deivce) => {
-//!                         println!("Remove:{}",deivce.id());
+//!                     EventKind::DeviceRemove(info, reason) => {
+//!                         println!("Remove:{} {}",info.device_name,reason);
 //!                     },
+//!                     _ => {},
 //!                 }
 //!             },
 //!             Err(e) => println!("error: {:?}",e),
@@ -54,44 +56,19 @@ pub mod peripheral;
 pub mod api;
 pub mod core;
 pub mod enums;
-
-
-#[cfg(test)]
-mod tests {
-    use crate::core::App;
-
-    use super::*;
-
-
-    macro_rules! aw {
-        ($e:expr) => {
-            tokio_test::block_on($e)
-        };
-      }
-
-
-    #[test]
-    fn it_works() {
-
-        
-        let app = aw!(App::start(None)).unwrap();
-
-        let mut channl = app.register_broadcast().unwrap();
-
-        loop {
-            match aw!(channl.recv()) {
-                Ok(v) => {
-                    match v {
-                        api::CoreEvent::DeviceAdd(id) => {
-                            println!("Add:{:?}",id);
-                        },
-                        api::CoreEvent::DeviceRemove(id) => {
-                            println!("Remove:{}",id);
-                        },
-                    }
-                },
-                Err(e) => println!("error: {:?}",e),
-            }
-        }
-    }
-}
+pub mod auth;
+pub mod reconnect;
+pub mod version;
+pub mod matcher;
+pub mod stream;
+pub mod blocking;
+pub mod logical;
+pub mod filter;
+pub mod probe;
+pub mod profile;
+#[cfg(feature = "config")]
+pub mod config;
+mod registry;
+mod debounce;
+mod handler;
+mod tasks;
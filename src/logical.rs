@@ -0,0 +1,262 @@
+use std::sync::{Arc, RwLock};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use dashmap::DashMap;
+use uuid::Uuid;
+
+use crate::{
+    api::PeripheralApi,
+    enums::{ChipManufacturer, ChipType, ConnectionType, DeviceType, Error, PeripheralState},
+    peripheral::{Peripheral, PeripheralInfo},
+    version::Version,
+};
+
+/// 逻辑设备标识解析，用于通过协议读取设备唯一 id，返回 None 时使用 VID + 设备序列号。
+/// 返回的 id 需要在所有产品之间唯一，相同 id 的设备会合并为同一个逻辑设备
+#[async_trait]
+pub trait IdentityResolver: Send + Sync {
+    async fn unique_id(&self, device: &Peripheral) -> Result<Option<String>>;
+}
+
+/// 逻辑设备，同一台物理设备的 USB 和 BLE 连接合并为一个对象
+///
+/// 读写优先使用首选连接，首选连接断开或出错时切换到其他连接。
+/// 没有序列号且 IdentityResolver 未返回唯一 id 的设备无法判断是否为同一物理设备，
+/// 不会生成逻辑设备，只能通过 `App::peripherals` 访问。
+#[derive(Debug, Clone)]
+pub struct LogicalDevice {
+    shared: Arc<LogicalShared>,
+}
+
+#[derive(Debug)]
+struct LogicalShared {
+    id: Uuid,
+    /// VID + 序列号，或协议上报的唯一 id
+    key: String,
+    /// 首选连接类型
+    preferred: ConnectionType,
+    links: RwLock<Vec<Peripheral>>,
+    /// 最后一个连接的设备信息，所有连接都断开后使用
+    last_info: RwLock<PeripheralInfo>,
+}
+
+impl LogicalDevice {
+    fn new(key: String, preferred: ConnectionType, peripheral: &Peripheral) -> Self {
+        LogicalDevice {
+            shared: Arc::new(LogicalShared {
+                id: Uuid::new_v4(),
+                key,
+                preferred,
+                links: RwLock::new(vec![peripheral.clone()]),
+                last_info: RwLock::new(peripheral.info()),
+            })
+        }
+    }
+
+    /// 逻辑设备的唯一标识
+    pub fn key(&self) -> &str {
+        &self.shared.key
+    }
+
+    /// 当前所有连接
+    pub fn links(&self) -> Vec<Peripheral> {
+        self.shared.links.read().unwrap().clone()
+    }
+
+    /// 当前使用的连接
+    pub fn active(&self) -> Option<Peripheral> {
+        self.candidates().into_iter().next()
+    }
+
+    /// 可用的连接，首选连接在前
+    fn candidates(&self) -> Vec<Peripheral> {
+        let mut links: Vec<Peripheral> = self.links().into_iter()
+            .filter(|p| p.state() == PeripheralState::Connected)
+            .collect();
+        links.sort_by_key(|p| p.conn_type() != self.shared.preferred);
+        links
+    }
+
+    fn info(&self) -> PeripheralInfo {
+        match self.active() {
+            Some(peripheral) => peripheral.info(),
+            None => self.shared.last_info.read().unwrap().clone(),
+        }
+    }
+
+    fn attach(&self, peripheral: &Peripheral) {
+        let mut links = self.shared.links.write().unwrap();
+        links.retain(|p| p.id() != peripheral.id());
+        links.push(peripheral.clone());
+        *self.shared.last_info.write().unwrap() = peripheral.info();
+    }
+
+    /// 移除连接，返回剩余连接数
+    fn detach(&self, id: &Uuid) -> usize {
+        let mut links = self.shared.links.write().unwrap();
+        links.retain(|p| p.id() != *id);
+        links.len()
+    }
+}
+
+#[async_trait]
+impl PeripheralApi for LogicalDevice {
+    fn id(&self) -> Uuid {
+        self.shared.id
+    }
+
+    fn address(&self) -> String {
+        self.info().address
+    }
+
+    fn conn_type(&self) -> ConnectionType {
+        self.info().conn_type
+    }
+
+    fn vendor_id(&self) -> u16 {
+        self.info().vid
+    }
+
+    fn product_id(&self) -> u16 {
+        self.info().pid
+    }
+
+    fn chip_manufacturer(&self) -> ChipManufacturer {
+        self.info().chip_manufacturer
+    }
+
+    fn device_type(&self) -> DeviceType {
+        self.info().device_type
+    }
+
+    fn device_name(&self) -> String {
+        self.info().device_name
+    }
+
+    fn chip_type(&self) -> ChipType {
+        self.info().chip_type
+    }
+
+    fn software_version(&self) -> Version {
+        self.info().software_version
+    }
+
+    fn hardware_version(&self) -> Version {
+        self.info().hardware_version
+    }
+
+    fn firmware_version(&self) -> Version {
+        self.info().firmware_version
+    }
+
+    fn serial_number(&self) -> String {
+        self.info().serial_number
+    }
+
+    fn state(&self) -> PeripheralState {
+        match self.active() {
+            Some(_) => PeripheralState::Connected,
+            None => PeripheralState::Disconnected,
+        }
+    }
+
+    /// 重新连接所有连接，任一成功即可
+    async fn connect(&self, u: Uuid) -> Result<()> {
+        if u != self.shared.id {
+            anyhow::bail!(Error::DeviceNotFound)
+        }
+        let mut result = Err(Error::NotConnected.into());
+        for link in self.links() {
+            if let Ok(()) = link.connect(link.id()).await {
+                result = Ok(());
+            }
+        }
+        result
+    }
+
+    async fn read<'a>(&'a self, buf: &'a mut [u8]) -> Result<usize> {
+        let mut last_err = None;
+        for link in self.candidates() {
+            match link.read(buf).await {
+                Ok(len) => return Ok(len),
+                Err(e) => last_err = Some(e),
+            }
+        }
+        Err(last_err.unwrap_or_else(|| Error::NotConnected.into()))
+    }
+
+    async fn write<'a>(&'a self, src: &'a [u8]) -> Result<usize> {
+        let mut last_err = None;
+        for link in self.candidates() {
+            match link.write(src).await {
+                Ok(len) => return Ok(len),
+                Err(e) => last_err = Some(e),
+            }
+        }
+        Err(last_err.unwrap_or_else(|| Error::NotConnected.into()))
+    }
+
+    async fn request<'a>(&'a self, src: &'a [u8]) -> Result<Vec<u8>> {
+        let mut last_err = None;
+        for link in self.candidates() {
+            match link.request(src).await {
+                Ok(data) => return Ok(data),
+                Err(e) => last_err = Some(e),
+            }
+        }
+        Err(last_err.unwrap_or_else(|| Error::NotConnected.into()))
+    }
+
+    /// 断开所有连接，某个连接出错时仍继续断开其余连接，返回第一个错误
+    async fn disconnect(&self) -> Result<()> {
+        let mut result = Ok(());
+        for link in self.links() {
+            if let Err(e) = link.disconnect().await {
+                if result.is_ok() {
+                    result = Err(e);
+                }
+            }
+        }
+        result
+    }
+}
+
+/// 按唯一标识维护逻辑设备
+pub(crate) struct LogicalRegistry {
+    preferred: ConnectionType,
+    devices: DashMap<String, LogicalDevice>,
+    /// 连接 id -> 逻辑设备标识
+    keys: DashMap<Uuid, String>,
+}
+
+impl LogicalRegistry {
+    pub(crate) fn new(preferred: ConnectionType) -> Self {
+        LogicalRegistry {
+            preferred,
+            devices: DashMap::new(),
+            keys: DashMap::new(),
+        }
+    }
+
+    pub(crate) fn attach(&self, key: String, peripheral: &Peripheral) {
+        self.keys.insert(peripheral.id(), key.clone());
+        self.devices.entry(key.clone())
+            .and_modify(|d| d.attach(peripheral))
+            .or_insert_with(|| LogicalDevice::new(key, self.preferred, peripheral));
+    }
+
+    pub(crate) fn detach(&self, id: &Uuid) {
+        if let Some((_, key)) = self.keys.remove(id) {
+            self.devices.remove_if(&key, |_, d| d.detach(id) == 0);
+        }
+    }
+
+    pub(crate) fn get(&self, key: &str) -> Option<LogicalDevice> {
+        self.devices.get(key).map(|d| d.clone())
+    }
+
+    pub(crate) fn list(&self) -> Vec<LogicalDevice> {
+        self.devices.iter().map(|d| d.clone()).collect()
+    }
+}
@@ -1,61 +1,254 @@
-use uuid::Uuid;
+//! pmctl：查看本机识别到的外围设备，监听设备事件
+//!
+//! ```text
+//! pmctl list [--json] [--config pm.toml] [--vid 0x3373] [--pid 0x0010] [--name '^K1'] [--transport usb]
+//! pmctl monitor [--json] [--no-notifications] [过滤参数同 list]
+//! ```
 
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    println!("1");
+use std::{path::PathBuf, time::Duration};
 
-    use peripheral_manager::{
-        core::{AppOptions,App},
-        api::{PeripheralApi},
-        enums::CoreEvent,
-    };
-    // use btleplug::api::{Peripheral as _,bleuuid::uuid_from_u16};
-    //
-    // /// 通信uuid
-    // const WRITE_READ_NOTIFY_UUID: Uuid = uuid_from_u16(0xFF01);
-    //
-    // let options = AppOptions::new().set_broadcast(true, 10).
-    // set_usb_filter(Box::new(|x| x.vendor_id == 0x3373 && x.input_report_byte_length == 65)).
-    // set_ble_filter(Box::new(|peripheral| {
-    //     match peripheral.characteristics().iter()
-    //     .find(|c| c.uuid == WRITE_READ_NOTIFY_UUID) {
-    //         Some(_) => true,
-    //         None => false,
-    //     }
-    // }));
-    //
-    // let app = App::start(Some(options)).await.unwrap();
-    //
-    // let all_devices = app.peripherals().await.unwrap();
-    // println!("all device len:{}",all_devices.len());
-    //
-    // let mut channel = app.register_broadcast().unwrap();
-    //
-    // loop {
-    //     match channel.recv().await {
-    //         Ok(v) => {
-    //             match v {
-    //                 CoreEvent::DeviceAdd(id) => {
-    //                     println!("add device:{}",id.id());
-    //
-    //                     let device = app.peripheral(&id.id()).await.unwrap();
-    //
-    //                     println!("device path :{:?}",device.address());
-    //                     let mut buffer = [0; 51];
-    //                     println!("read len:{:?}",device.read(&mut buffer).await?);
-    //                     println!("read data {:?}",buffer);
-    //
-    //                     let write_buf = [2;2];
-    //                     let write_len = device.write(&write_buf).await?;
-    //                     println!("write_len:{}",write_len);
-    //                 },
-    //                 CoreEvent::DeviceRemove(id) => {
-    //                     println!("Remove:{:?}",id);
-    //                 },
-    //             }
-    //         },
-    //         Err(e) => println!("error: {:?}",e),
-    //     }
-    // }
+use anyhow::{Result, anyhow};
+use clap::{Args, Parser, Subcommand};
+use futures::StreamExt;
+use serde::Serialize;
+
+use peripheral_manager::{
+    core::{App, AppOptions},
+    enums::{ConnectionType, CoreEvent, EventKind, EventRecord},
+    filter::{FilterRule, FilterRules, IdRange},
+    matcher::DeviceMatcher,
+    peripheral::PeripheralInfo,
+};
+
+#[derive(Parser, Debug)]
+#[command(name = "pmctl", version, about = "外围设备管理命令行工具")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// 列出当前已加入（已过滤、已认证）的设备
+    List(ListArgs),
+    /// 持续输出设备事件（热插拔、重连、notify 数据等），Ctrl+C 退出
+    Monitor(MonitorArgs),
+}
+
+/// 设备过滤参数，命令行条件会替换配置文件中的过滤规则
+#[derive(Args, Debug)]
+struct FilterArgs {
+    /// TOML/JSON 配置文件
+    #[arg(long, short)]
+    config: Option<PathBuf>,
+    /// 厂商 id，如 0x3373
+    #[arg(long, value_parser = parse_id)]
+    vid: Option<u16>,
+    /// 产品 id，如 0x0010
+    #[arg(long, value_parser = parse_id)]
+    pid: Option<u16>,
+    /// 设备名称正则
+    #[arg(long)]
+    name: Option<String>,
+    /// 只启用一种连接类型：usb 或 ble
+    #[arg(long, value_parser = parse_transport)]
+    transport: Option<ConnectionType>,
+}
+
+#[derive(Args, Debug)]
+struct ListArgs {
+    #[command(flatten)]
+    filter: FilterArgs,
+    /// 以 JSON 输出
+    #[arg(long)]
+    json: bool,
+    /// 启动后等待设备连接的时间，毫秒
+    #[arg(long, default_value_t = 1500)]
+    wait: u64,
+}
+
+#[derive(Args, Debug)]
+struct MonitorArgs {
+    #[command(flatten)]
+    filter: FilterArgs,
+    /// 以 JSON Lines 输出，每行一个事件
+    #[arg(long)]
+    json: bool,
+    /// 不输出设备 notify 数据（只有 BLE 设备产生 notify 事件）
+    #[arg(long)]
+    no_notifications: bool,
+}
+
+fn parse_id(s: &str) -> Result<u16> {
+    let s = s.trim();
+    match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => u16::from_str_radix(hex, 16),
+        None => s.parse::<u16>(),
+    }
+    .map_err(|_| anyhow!("invalid id: {}", s))
+}
+
+fn parse_transport(s: &str) -> Result<ConnectionType> {
+    s.to_ascii_uppercase().parse::<ConnectionType>().map_err(|_| anyhow!("expected usb or ble, got {}", s))
+}
+
+impl FilterArgs {
+    /// 按配置文件和命令行参数生成启动参数，适配器不可用时继续运行
+    fn options(&self) -> Result<AppOptions> {
+        let mut options = match &self.config {
+            Some(path) => AppOptions::from_file(path)?,
+            None => AppOptions::default(),
+        };
+        let mut rules = Vec::new();
+        if let Some(vid) = self.vid {
+            rules.push(FilterRule::VendorId(IdRange::Exact(vid)));
+        }
+        if let Some(pid) = self.pid {
+            rules.push(FilterRule::ProductId(IdRange::Exact(pid)));
+        }
+        if let Some(name) = &self.name {
+            rules.push(FilterRule::NameRegex(name.clone()));
+        }
+        if !rules.is_empty() {
+            let rule = FilterRule::All(rules);
+            options = options.set_filter_rules(FilterRules { usb: Some(rule.clone()), ble: Some(rule) })?;
+        }
+        if let Some(transport) = self.transport {
+            options = options.set_transports(transport == ConnectionType::USB, transport == ConnectionType::BLE);
+        }
+        Ok(options.set_best_effort(true))
+    }
+
+    /// 事件流的匹配条件
+    fn matcher(&self) -> DeviceMatcher {
+        let mut matcher = DeviceMatcher::new();
+        if let Some(vid) = self.vid {
+            matcher = match self.pid {
+                Some(pid) => matcher.vid_pid(vid, pid),
+                None => matcher.vid(vid),
+            };
+        }
+        if let Some(transport) = self.transport {
+            matcher = matcher.connection(transport);
+        }
+        matcher
+    }
+}
+
+/// 启动 App，不可用的适配器输出到 stderr
+async fn start(filter: &FilterArgs) -> Result<App> {
+    let app = App::start(Some(filter.options()?)).await?;
+    for conn_type in [ConnectionType::USB, ConnectionType::BLE] {
+        if let Some(state) = app.adapter_state(conn_type) {
+            eprintln!("{} adapter: {:?}", conn_type, state);
+        }
+    }
+    Ok(app)
+}
+
+async fn list(args: ListArgs) -> Result<()> {
+    let app = start(&args.filter).await?;
+    tokio::time::sleep(Duration::from_millis(args.wait)).await;
+
+    let mut infos: Vec<PeripheralInfo> = app.peripherals().await?.iter().map(|p| p.info()).collect();
+    infos.sort_by(|a, b| (a.conn_type.num(), &a.address).cmp(&(b.conn_type.num(), &b.address)));
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&infos)?);
+        return Ok(());
+    }
+    if infos.is_empty() {
+        println!("no peripherals");
+        return Ok(());
+    }
+    println!("{:<36}  {:<4}  {:<9}  {:<16}  {:<20}  {:<24}  ADDRESS", "ID", "CONN", "VID:PID", "CHIP", "TYPE", "SW/HW/FW");
+    for info in infos {
+        println!(
+            "{:<36}  {:<4}  {:04x}:{:04x}  {:<16}  {:<20}  {:<24}  {}",
+            info.id,
+            info.conn_type,
+            info.vid,
+            info.pid,
+            format!("{}/{}", info.chip_manufacturer, info.chip_type),
+            info.device_type,
+            format!("{}/{}/{}", info.software_version, info.hardware_version, info.firmware_version),
+            info.address,
+        );
+    }
     Ok(())
-}
\ No newline at end of file
+}
+
+/// JSON Lines 的一行
+#[derive(Serialize)]
+struct JsonLine {
+    time: String,
+    #[serde(flatten)]
+    record: EventRecord,
+}
+
+async fn monitor(args: MonitorArgs) -> Result<()> {
+    let app = start(&args.filter).await?;
+    // 先输出适配器状态和已加入的设备
+    let mut events = app.watch().matching(args.filter.matcher());
+    // 只创建一次，输出事件期间收到的 Ctrl+C 也不会丢失
+    let ctrl_c = tokio::signal::ctrl_c();
+    tokio::pin!(ctrl_c);
+    loop {
+        let event = tokio::select! {
+            event = events.next() => match event {
+                Some(event) => event,
+                None => break,
+            },
+            _ = &mut ctrl_c => break,
+        };
+        if args.no_notifications && matches!(event.kind, EventKind::Notification(..)) {
+            continue;
+        }
+        if args.json {
+            let line = JsonLine { time: humantime::format_rfc3339_millis(event.timestamp).to_string(), record: event.record() };
+            println!("{}", serde_json::to_string(&line)?);
+        } else {
+            println!("{}", describe(&event));
+        }
+    }
+    Ok(())
+}
+
+/// 事件的单行描述
+fn describe(event: &CoreEvent) -> String {
+    let time = humantime::format_rfc3339_millis(event.timestamp);
+    let conn = event.conn_type.map_or("-".to_string(), |c| c.to_string());
+    let text = match &event.kind {
+        EventKind::DeviceAdd(peripheral) => {
+            let info = peripheral.info();
+            format!("add        {} {:04x}:{:04x} {} {:?}", info.id, info.vid, info.pid, info.device_type, info.device_name)
+        },
+        EventKind::DeviceRemove(info, reason) => {
+            format!("remove     {} {:04x}:{:04x} {:?} ({})", info.id, info.vid, info.pid, info.device_name, reason)
+        },
+        EventKind::DeviceUpdated(id, changes) => {
+            let changes: Vec<String> = changes.iter().map(|c| format!("{}: {} -> {}", c.field, c.old, c.new)).collect();
+            format!("updated    {} {}", id, changes.join(", "))
+        },
+        EventKind::AdapterStateChanged(state) => format!("adapter    {:?}", state),
+        EventKind::IdentificationFailed(id, reason) => format!("unknown    {} {}", id, reason),
+        EventKind::AuthenticationRejected(id, reason) => format!("rejected   {} {}", id, reason),
+        EventKind::ConnectionLost(id) => format!("lost       {}", id),
+        EventKind::Reconnecting(id, attempt) => format!("reconnect  {} attempt {}", id, attempt),
+        EventKind::Reconnected(id) => format!("reconnected {}", id),
+        EventKind::Notification(id, data) => {
+            let hex: Vec<String> = data.iter().map(|b| format!("{:02x}", b)).collect();
+            format!("notify     {} {}", id, hex.join(" "))
+        },
+        EventKind::Resync(missed) => format!("resync     {} events missed", missed),
+    };
+    format!("{} {:<4} {}", time, conn, text)
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    match Cli::parse().command {
+        Command::List(args) => list(args).await,
+        Command::Monitor(args) => monitor(args).await,
+    }
+}
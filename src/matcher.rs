@@ -0,0 +1,112 @@
+use crate::{
+    enums::{ConnectionType, DeviceType},
+    peripheral::PeripheralInfo,
+};
+
+/// 设备匹配条件，未设置的条件匹配所有设备
+#[derive(Debug, Clone, Default)]
+pub struct DeviceMatcher {
+    device_type: Option<DeviceType>,
+    conn_type: Option<ConnectionType>,
+    vid: Option<u16>,
+    pid: Option<u16>,
+}
+
+impl DeviceMatcher {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 匹配设备类型
+    pub fn device_type(mut self, device_type: DeviceType) -> Self {
+        self.device_type = Some(device_type);
+        self
+    }
+
+    /// 匹配连接类型
+    pub fn connection(mut self, conn_type: ConnectionType) -> Self {
+        self.conn_type = Some(conn_type);
+        self
+    }
+
+    /// 匹配厂商 id
+    pub fn vid(mut self, vid: u16) -> Self {
+        self.vid = Some(vid);
+        self
+    }
+
+    /// 匹配厂商 id 和产品 id
+    pub fn vid_pid(mut self, vid: u16, pid: u16) -> Self {
+        self.vid = Some(vid);
+        self.pid = Some(pid);
+        self
+    }
+
+    pub fn matches(&self, info: &PeripheralInfo) -> bool {
+        self.matches_connection(info.conn_type)
+            && self.device_type.as_ref().map_or(true, |t| *t == info.device_type)
+            && self.vid.map_or(true, |v| v == info.vid)
+            && self.pid.map_or(true, |p| p == info.pid)
+    }
+
+    pub fn matches_connection(&self, conn_type: ConnectionType) -> bool {
+        self.conn_type.map_or(true, |c| c == conn_type)
+    }
+
+    /// 是否设置了连接类型以外的条件
+    pub(crate) fn has_device_criteria(&self) -> bool {
+        self.device_type.is_some() || self.vid.is_some() || self.pid.is_some()
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::{
+        enums::{ChipManufacturer, ChipType},
+        version::Version,
+    };
+
+    /// 测试用的设备信息
+    pub(crate) fn info(conn_type: ConnectionType, vid: u16, pid: u16, device_type: DeviceType) -> PeripheralInfo {
+        PeripheralInfo {
+            id: Uuid::new_v4(),
+            conn_type,
+            address: String::new(),
+            vid,
+            pid,
+            chip_manufacturer: ChipManufacturer::Unknown(None),
+            device_type,
+            device_name: String::new(),
+            reported_name: String::new(),
+            chip_type: ChipType::Unknown,
+            software_version: Version::from_raw("1.0.0"),
+            hardware_version: Version::from_raw("1.0.0"),
+            firmware_version: Version::from_raw("1.0.0"),
+            serial_number: String::new(),
+            manufacturer: String::new(),
+        }
+    }
+
+    #[test]
+    fn criteria() {
+        let keyboard = info(ConnectionType::USB, 0x3373, 0x0001, DeviceType::Keyboard);
+        assert!(DeviceMatcher::new().matches(&keyboard));
+        assert!(!DeviceMatcher::new().has_device_criteria());
+
+        assert!(DeviceMatcher::new().vid(0x3373).matches(&keyboard));
+        assert!(!DeviceMatcher::new().vid_pid(0x3373, 0x0002).matches(&keyboard));
+        assert!(!DeviceMatcher::new().device_type(DeviceType::Mouse).matches(&keyboard));
+        assert!(!DeviceMatcher::new().connection(ConnectionType::BLE).matches(&keyboard));
+        assert!(DeviceMatcher::new()
+            .connection(ConnectionType::USB)
+            .device_type(DeviceType::Keyboard)
+            .vid_pid(0x3373, 0x0001)
+            .matches(&keyboard));
+
+        // 只设置连接类型时不需要设备信息
+        assert!(!DeviceMatcher::new().connection(ConnectionType::USB).has_device_criteria());
+    }
+}
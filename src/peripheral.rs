@@ -1,4 +1,4 @@
-use std::{io::Read, sync::Arc, time::Duration, str::FromStr, mem};
+use std::{ffi::CString, io::Read, sync::{Arc, Mutex, RwLock}, time::Duration, str::FromStr, mem};
 
 
 use tokio::{time, sync::{broadcast,broadcast::Sender,broadcast::Receiver }};
@@ -12,13 +12,15 @@ use usb_manager::hid_device::HidDevice as UsbPeripheral;
 use btleplug::{
     
     platform::{Peripheral as BlePeripheral, PeripheralId},
-    api::{Peripheral as ApiPeripheral,bleuuid::uuid_from_u16,WriteType::WithResponse}
+    api::{Peripheral as ApiPeripheral,BDAddr,bleuuid::uuid_from_u16,WriteType::WithResponse}
 
 };
 
 use crate::{
-    enums::{Error,ConnectionType,ChipManufacturer,DeviceType,ChipType},
-    api::PeripheralApi
+    enums::{Error,ConnectionType,ChipManufacturer,DeviceType,ChipType,PeripheralState,CoreEvent,EventKind,InfoChange},
+    api::PeripheralApi,
+    version::Version,
+    profile::{DeviceProfile, ProfileRegistry},
 };
 
 use lazy_static::lazy_static;
@@ -39,8 +41,15 @@ const PNP_ID_UUID: Uuid = uuid_from_u16(0x2A50);
 const FIRMWARE_REVISION_UUID: Uuid = uuid_from_u16(0x2a26);
 /// Hardware Revision String
 const HARDWARE_REVISION_UUID: Uuid = uuid_from_u16(0x2a27);
-/// Hardware Revision String
+/// Software Revision String
 const SOFTWARE_REVISION_UUID: Uuid = uuid_from_u16(0x2a28);
+/// Serial Number String
+const SERIAL_NUMBER_UUID: Uuid = uuid_from_u16(0x2a25);
+/// Manufacturer Name String
+const MANUFACTURER_NAME_UUID: Uuid = uuid_from_u16(0x2a29);
+
+/// 默认的 request 应答超时
+pub(crate) const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(2);
 
 const BATTERY_SERVICE_UUID: Uuid = uuid_from_u16(0x180f);
 /// PnP ID  获取PID VID
@@ -51,26 +60,220 @@ use dashmap::DashMap;
 /// 外围设备对象
 #[derive(Debug, Clone)]
 pub enum Device {
-    Usb(UsbPeripheral), 
+    Usb(UsbDescriptor), 
     Ble(BlePeripheral)
 }
 
+/// USB HID 设备的描述信息，不持有打开的句柄
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UsbDescriptor {
+    pub path: CString,
+    pub vendor_id: u16,
+    pub product_id: u16,
+    pub serial_number: String,
+    /// bcdDevice
+    pub release_number: u16,
+    pub manufacturer_string: String,
+    pub product_string: String,
+    pub usage_page: u16,
+    pub usage: u16,
+    pub input_report_byte_length: u16,
+}
+
+impl UsbDescriptor {
+    /// 设备路径
+    pub fn address(&self) -> String {
+        self.path.clone().into_string().unwrap_or_default()
+    }
+
+    /// 按设备路径打开 HID 句柄
+    fn open(&self) -> Result<UsbPeripheral> {
+        Ok(UsbPeripheral::open_path(&self.path)?)
+    }
+}
+
+impl From<&UsbPeripheral> for UsbDescriptor {
+    fn from(device: &UsbPeripheral) -> Self {
+        UsbDescriptor {
+            path: device.path.clone(),
+            vendor_id: device.vendor_id,
+            product_id: device.product_id,
+            serial_number: device.serial_number.clone(),
+            release_number: device.release_number,
+            manufacturer_string: device.manufacturer_string.clone(),
+            product_string: device.product_string.clone(),
+            usage_page: device.usage_page as u16,
+            usage: device.usage as u16,
+            input_report_byte_length: device.input_report_byte_length as u16,
+        }
+    }
+}
+
+
+/// 内部连接状态，区分意外断开和主动断开
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Link {
+    Connected,
+    /// 连接意外断开
+    Lost,
+    /// 调用 disconnect 主动断开
+    Closed,
+}
+
+impl Link {
+    /// 连接断开，主动断开的状态保持不变
+    fn lost(self) -> Link {
+        match self {
+            Link::Closed => Link::Closed,
+            _ => Link::Lost,
+        }
+    }
+
+    fn state(self) -> PeripheralState {
+        match self {
+            Link::Connected => PeripheralState::Connected,
+            Link::Lost | Link::Closed => PeripheralState::Disconnected,
+        }
+    }
+}
 
 #[derive(Debug)]
 pub struct PeripheralDevice{
-    pub device:Device,
+    /// USB 设备在窗口期内重新插入时替换，USB 只保存描述信息
+    pub device:RwLock<Device>,
     pub sender:Sender<Vec<u8>>,
-    // 线程句柄
-    _thread_handle: Option<tokio::task::JoinHandle<()>>,
+    /// 连接状态
+    link: RwLock<Link>,
+    /// 打开的 HID 句柄，唯一持有者，断开时释放，BLE 为 None
+    usb_handle: Mutex<Option<UsbPeripheral>>,
+    /// BLE request 等待应答的超时
+    request_timeout: RwLock<Duration>,
+    // notify 线程句柄
+    notify_handle: Mutex<Option<tokio::task::JoinHandle<()>>>,
 }
 
 
 impl PeripheralDevice {
+    fn new(device: Device, usb_handle: Option<UsbPeripheral>, sender: Sender<Vec<u8>>, notify_handle: Option<tokio::task::JoinHandle<()>>) -> Self {
+        PeripheralDevice {
+            device: RwLock::new(device),
+            sender,
+            link: RwLock::new(Link::Connected),
+            usb_handle: Mutex::new(usb_handle),
+            request_timeout: RwLock::new(DEFAULT_REQUEST_TIMEOUT),
+            notify_handle: Mutex::new(notify_handle),
+        }
+    }
+
+    fn state(&self) -> PeripheralState {
+        self.link.read().unwrap().state()
+    }
+
+    fn device(&self) -> Device {
+        self.device.read().unwrap().clone()
+    }
+
+    /// USB 设备重新插入，替换描述信息和 HID 句柄，主动断开的设备保持断开并关闭新句柄
+    fn replace_usb(&self, device: UsbPeripheral) {
+        *self.device.write().unwrap() = Device::Usb(UsbDescriptor::from(&device));
+        if !self.user_disconnected() {
+            *self.usb_handle.lock().unwrap() = Some(device);
+            self.set_link(Link::Connected);
+        }
+    }
+
+    fn set_link(&self, link: Link) {
+        *self.link.write().unwrap() = link;
+    }
+
+    /// 在阻塞线程中使用打开的 HID 句柄，已断开时返回 NotConnected
+    ///
+    /// HID 读写是同步调用，放在 spawn_blocking 中执行，不占用运行时的工作线程，
+    /// 调用方的 timeout 才能生效。超时后阻塞调用仍会继续，结束后释放句柄
+    async fn with_usb<T, F>(&self, f: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&UsbPeripheral) -> Result<T> + Send + 'static,
+    {
+        let handle = match self.usb_handle.lock().unwrap().clone() {
+            Some(handle) => handle,
+            None => bail!(Error::NotConnected),
+        };
+        tokio::task::spawn_blocking(move || f(&handle)).await?
+    }
+
+    fn check_connected(&self) -> Result<()> {
+        if self.state() != PeripheralState::Connected {
+            bail!(Error::NotConnected)
+        }
+        Ok(())
+    }
+
+    /// 停止 notify 线程
+    fn stop_notify(&self) {
+        if let Some(handle) = self.notify_handle.lock().unwrap().take() {
+            handle.abort();
+        }
+    }
+
+    /// 重新建立连接，USB 重新打开 HID 句柄，BLE 重新连接 GATT 并订阅 notify
+    async fn connect(&self) -> Result<()> {
+        match &self.device() {
+            Device::Usb(device) => {
+                let mut handle = self.usb_handle.lock().unwrap();
+                if handle.is_none() {
+                    *handle = Some(device.open()?);
+                }
+            },
+            Device::Ble(device) => {
+                if !device.is_connected().await? {
+                    device.connect().await?;
+                }
+                device.discover_services().await?;
+                self.stop_notify();
+                *self.notify_handle.lock().unwrap() = Some(spawn_notify(device.clone(), self.sender.clone()));
+            },
+        }
+        self.set_link(Link::Connected);
+        Ok(())
+    }
+
+    /// 主动断开连接，停止 notify 线程，USB 释放 HID 句柄，BLE 断开 GATT 连接
+    async fn disconnect(&self) -> Result<()> {
+        self.stop_notify();
+        self.set_link(Link::Closed);
+        match &self.device() {
+            Device::Usb(_) => {
+                self.usb_handle.lock().unwrap().take();
+            },
+            Device::Ble(device) => {
+                if device.is_connected().await? {
+                    device.disconnect().await?;
+                }
+            },
+        }
+        Ok(())
+    }
+
+    /// 是否由 disconnect 主动断开
+    fn user_disconnected(&self) -> bool {
+        *self.link.read().unwrap() == Link::Closed
+    }
+
+    /// 连接意外断开，USB 释放失效的 HID 句柄
+    fn link_lost(&self) {
+        self.stop_notify();
+        self.usb_handle.lock().unwrap().take();
+        let link = self.link.read().unwrap().lost();
+        self.set_link(link);
+    }
+
     async fn read<'a>(&'a self,buf: &'a mut[u8])->  Result<usize>{
+        self.check_connected()?;
         let len = buf.len();
-        return match &self.device {
-            Device::Usb(device) => {
-                let result = device.get_input_report(0x00, len)?;
+        return match &self.device() {
+            Device::Usb(_) => {
+                let result = self.with_usb(move |device| device.get_input_report(0x00, len)).await?;
                 result.as_slice().read(buf).map_err(|e| e.into())
             },
             Device::Ble(device) =>{
@@ -85,10 +288,12 @@ impl PeripheralDevice {
     }
 
     async fn write<'a>(&'a self,src: &'a[u8]) ->  Result<usize> {
+        self.check_connected()?;
         let len = src.len();
-        return match &self.device {
-            Device::Usb(device) => {
-                device.set_output_report(0x00, src)?;
+        return match &self.device() {
+            Device::Usb(_) => {
+                let data = src.to_vec();
+                self.with_usb(move |device| device.set_output_report(0x00, &data)).await?;
                 Ok(len)
             },
             Device::Ble(device) =>{
@@ -103,11 +308,15 @@ impl PeripheralDevice {
     }
 
     async fn request<'a>(&'a self,src: &'a[u8]) -> Result<Vec<u8>>  {
+        self.check_connected()?;
         let len = src.len();
-        return match &self.device {
-            Device::Usb(device) => {
-                device.set_output_report(0x00, src)?;
-                device.get_input_report(0x00, len)
+        return match &self.device() {
+            Device::Usb(_) => {
+                let data = src.to_vec();
+                self.with_usb(move |device| {
+                    device.set_output_report(0x00, &data)?;
+                    device.get_input_report(0x00, len)
+                }).await
             },
             Device::Ble(device) =>{
                 // 检查服务状态
@@ -117,15 +326,69 @@ impl PeripheralDevice {
                 let mut rece = self.sender.subscribe();
                 // 写入操作命令
                 device.write_by_uuid(&SERVICE_UUID,&WRITE_READ_NOTIFY_UUID,src,WithResponse).await?;
-                let result = time::timeout(Duration::from_secs(2), async move{
+                let timeout = *self.request_timeout.read().unwrap();
+                let result = time::timeout(timeout, async move{
                     rece.recv().await
                 }).await;
-                result.map_err(|_| Error::TimedOut(Duration::from_secs(1)))?.map_err(|e| anyhow!(e))
+                result.map_err(|_| Error::TimedOut(timeout))?.map_err(|e| anyhow!(e))
             }
         }
     }
 }
 
+impl Drop for PeripheralDevice {
+    fn drop(&mut self) {
+        self.stop_notify();
+    }
+}
+
+/// 订阅 notify 返回，转发到 sender
+fn spawn_notify(ble: BlePeripheral, send: Sender<Vec<u8>>) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move{
+        // 订阅notify返回
+        if let Err(e) = ble.subscribe_by_uuid(&SERVICE_UUID,&WRITE_READ_NOTIFY_UUID).await{
+            println!("subscribe error:{}",e);
+        }
+        if let Ok(mut stream) = ble.notifications().await{
+            // Process while the BLE connection is not broken or stopped.
+            while let Some(data) = stream.next().await {
+                // println!("recv data:{:?}",data.value);
+                if let Err(e) = send.send(data.value){
+                    println!("send error:{}",e);
+                    break;
+                }
+            }
+        }
+    })
+}
+
+/// USB 设备描述符中的 bcdDevice，格式为 JJ.M.N
+fn bcd_version(release_number: u16) -> Version {
+    Version::from_raw(&format!("{:x}.{:x}.{:x}", release_number >> 8, (release_number >> 4) & 0xf, release_number & 0xf))
+}
+
+/// USB 设备的标识，有序列号时为 VID/PID + 序列号，同一设备的多个 HID 接口标识相同；
+/// 没有序列号时为设备路径，无法识别同一设备的多个接口
+pub(crate) fn usb_identity(device: &UsbDescriptor) -> String {
+    if device.serial_number.is_empty() {
+        format!("{}:{}", ConnectionType::USB, device.address())
+    } else {
+        format!("{}:{:04x}:{:04x}:{}", ConnectionType::USB, device.vendor_id, device.product_id, device.serial_number)
+    }
+}
+
+/// BLE 设备的标识
+pub(crate) fn ble_identity(address: &BDAddr) -> String {
+    format!("{}:{}", ConnectionType::BLE, address)
+}
+
+/// 由蓝牙地址生成设备 uuid，断开重连后保持不变
+pub(crate) fn ble_uuid(id: &PeripheralId) -> Uuid {
+    let mut slice = [0u8; 16];
+    slice[..6].clone_from_slice(&id.0.into_inner());
+    Uuid::from_bytes(slice)
+}
+
 /// 外围设备信息
 #[derive(Debug,Clone)]
 pub struct Peripheral {
@@ -136,49 +399,184 @@ pub struct Peripheral {
 struct Shared {
     /// 
     pub id: Uuid,
+    /// USB 设备重新插入后可能变化
+    pub address:RwLock<String>,
+    /// 区分物理设备的标识，去抖时按此判断是否为同一设备
+    pub identity: String,
+    /// 设备信息，refresh_info 时更新
+    pub info: RwLock<Info>,
+    /// 事件广播，设备加入 App 后设置
+    pub announcer: RwLock<Option<Sender<CoreEvent>>>,
+    /// 型号表，刷新设备信息后重新匹配型号
+    pub profiles: RwLock<Option<Arc<ProfileRegistry>>>,
+    /// 型号表中匹配的型号
+    pub profile: RwLock<Option<DeviceProfile>>,
+    /// 外围设备 
+    pub peripheral_device: PeripheralDevice,
+}
+
+/// 设备身份信息
+#[derive(Debug, Clone)]
+struct Info {
     pub vid: u16,
     pub pid: u16,
-    pub address:String,
     /// 厂商
     pub chip_manufacturer: ChipManufacturer,
     /// 设备类型
     pub device_type: DeviceType,
-    /// 设备名称
+    /// 设备名称，型号表设置了显示名称时为显示名称
     pub device_name: String,
+    /// 设备上报的名称，不被型号表覆盖，过滤规则和型号表按此匹配
+    pub reported_name: String,
     /// 芯片类型
     pub chip_type: ChipType,
     /// 软件版本号
-    pub software_version: String,
+    pub software_version: Version,
     /// 硬件版本号
-    pub hardware_version: String,
+    pub hardware_version: Version,
     /// 固件版本号
-    pub firmware_version: String,
-    /// 外围设备 
-    pub peripheral_device: PeripheralDevice,
+    pub firmware_version: Version,
+    /// 序列号，未提供时为空
+    pub serial_number: String,
+    /// 厂商名称，未提供时为空
+    pub manufacturer: String,
+}
+
+impl Info {
+    /// 从 HID 描述信息生成
+    fn from_usb(device: &UsbDescriptor) -> Self {
+        Info {
+            vid:device.vendor_id,
+            pid:device.product_id,
+            // 芯片和设备类型由型号表识别
+            chip_manufacturer: ChipManufacturer::Unknown(None),
+            device_type: DeviceType::Other,
+            device_name: "default".to_string(),
+            reported_name: device.product_string.clone(),
+            chip_type: ChipType::Unknown,
+            software_version: Version::from_raw("0.0.0"),
+            hardware_version: Version::from_raw("0.0.0"),
+            firmware_version: bcd_version(device.release_number),
+            serial_number: device.serial_number.clone(),
+            manufacturer: device.manufacturer_string.clone(),
+        }
+    }
+
+    /// 通过打开的 HID 句柄重新请求描述字符串和版本号
+    fn query_usb(device: &UsbPeripheral) -> Result<Self> {
+        let mut info = Info::from_usb(&UsbDescriptor::from(device));
+        info.serial_number = device.get_serial_number_string()?.unwrap_or_default();
+        info.manufacturer = device.get_manufacturer_string()?.unwrap_or_default();
+        info.firmware_version = bcd_version(device.get_device_info()?.release_number());
+        Ok(info)
+    }
+
+    /// 从 Device Information Service 读取
+    async fn from_ble(device: &BlePeripheral) -> Result<Self> {
+        let pnp = device.read_by_uuid(&DEVICE_INFO_SERVICE_UUID, &PNP_ID_UUID).await?;
+        let firmware_revision = device.read_by_uuid(&DEVICE_INFO_SERVICE_UUID, &FIRMWARE_REVISION_UUID).await?;
+        let hardware_revision = device.read_by_uuid(&DEVICE_INFO_SERVICE_UUID, &HARDWARE_REVISION_UUID).await?;
+        let software_revision = device.read_by_uuid(&DEVICE_INFO_SERVICE_UUID, &SOFTWARE_REVISION_UUID).await?;
+        // 序列号为可选特征
+        let serial_number = device.read_by_uuid(&DEVICE_INFO_SERVICE_UUID, &SERIAL_NUMBER_UUID).await.unwrap_or_default();
+        let manufacturer = device.read_by_uuid(&DEVICE_INFO_SERVICE_UUID, &MANUFACTURER_NAME_UUID).await.unwrap_or_default();
+        let vid:u16 = ((pnp[2] as u16) << 8) | pnp[1] as u16;
+        let pid:u16 = ((pnp[4] as u16) << 8) | pnp[3] as u16;
+
+        let mut name = "default".to_string();
+        if let Ok(propert) = device.properties().await {
+            name = propert.unwrap_or_default().local_name.unwrap_or_default();
+            println!("ble name:{}",name);
+        }
+
+        Ok(Info {
+            vid:vid,
+            pid:pid,
+            chip_manufacturer: ChipManufacturer::Unknown(None),
+            device_type: DeviceType::Other,
+            device_name: name.clone(),
+            reported_name: name,
+            chip_type: ChipType::Unknown,
+            software_version: Version::from_bytes(&software_revision),
+            hardware_version: Version::from_bytes(&hardware_revision),
+            firmware_version: Version::from_bytes(&firmware_revision),
+            serial_number: String::from_utf8_lossy(&serial_number).trim_end_matches('\0').to_string(),
+            manufacturer: String::from_utf8_lossy(&manufacturer).trim_end_matches('\0').to_string(),
+        })
+    }
+
+    /// 用型号表中的信息覆盖默认识别结果，设备上报的名称保持不变
+    fn apply(&mut self, profile: &DeviceProfile) {
+        self.chip_manufacturer = profile.chip_manufacturer.clone();
+        self.chip_type = profile.chip_type.clone();
+        self.device_type = profile.device_type.clone();
+        if let Some(name) = &profile.display_name {
+            self.device_name = name.clone();
+        }
+    }
+
+    /// 对比新旧信息，返回变化的字段
+    fn diff(&self, new: &Info) -> Vec<InfoChange> {
+        let mut changes = Vec::new();
+        let mut check = |field: &'static str, old: String, new: String| {
+            if old != new {
+                changes.push(InfoChange { field, old, new });
+            }
+        };
+        check("vid", format!("{:04x}", self.vid), format!("{:04x}", new.vid));
+        check("pid", format!("{:04x}", self.pid), format!("{:04x}", new.pid));
+        check("chip_manufacturer", self.chip_manufacturer.to_string(), new.chip_manufacturer.to_string());
+        check("device_type", self.device_type.to_string(), new.device_type.to_string());
+        check("device_name", self.device_name.clone(), new.device_name.clone());
+        check("chip_type", self.chip_type.to_string(), new.chip_type.to_string());
+        check("software_version", self.software_version.to_string(), new.software_version.to_string());
+        check("hardware_version", self.hardware_version.to_string(), new.hardware_version.to_string());
+        check("firmware_version", self.firmware_version.to_string(), new.firmware_version.to_string());
+        check("serial_number", self.serial_number.clone(), new.serial_number.clone());
+        check("manufacturer", self.manufacturer.clone(), new.manufacturer.clone());
+        changes
+    }
+}
+
+/// 设备信息快照，设备移除后仍可使用
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PeripheralInfo {
+    pub id: Uuid,
+    pub conn_type: ConnectionType,
+    /// USB 为设备路径，BLE 为蓝牙地址
+    pub address: String,
+    pub vid: u16,
+    pub pid: u16,
+    pub chip_manufacturer: ChipManufacturer,
+    pub device_type: DeviceType,
+    pub device_name: String,
+    /// 设备上报的名称（BLE 为广播名称，USB 为产品字符串），不被型号表的显示名称覆盖
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub reported_name: String,
+    pub chip_type: ChipType,
+    pub software_version: Version,
+    pub hardware_version: Version,
+    pub firmware_version: Version,
+    pub serial_number: String,
+    pub manufacturer: String,
 }
 
 impl Peripheral {
     /// 创建USB设备
     pub fn new_usb(device: UsbPeripheral) -> Self {
+        let descriptor = UsbDescriptor::from(&device);
         Peripheral {
             shared: Arc::new(Shared {
                 id:Uuid::new_v4(),
-                vid:device.vendor_id,
-                pid:device.product_id,
-                address:device.path.clone().into_string().unwrap_or_default(),
-                chip_manufacturer: ChipManufacturer::JL,
-                device_type: DeviceType::MulKeyboardTouchpad,
-                device_name: "default".to_string(),
-                chip_type: ChipType::AC635N,
-                software_version:"0.0.0".to_string(),
-                hardware_version: "0.0.0".to_string(),
-                firmware_version: "0.0.0".to_string(),
+                address:RwLock::new(descriptor.address()),
+                identity: usb_identity(&descriptor),
+                info: RwLock::new(Info::from_usb(&descriptor)),
+                announcer: RwLock::new(None),
+                profiles: RwLock::new(None),
+                profile: RwLock::new(None),
                 /// 外围设备 
-                peripheral_device: PeripheralDevice{
-                    device:Device::Usb(device),
-                    sender:broadcast::channel(0).0,
-                    _thread_handle: None,
-                },
+                peripheral_device: PeripheralDevice::new(Device::Usb(descriptor), Some(device), broadcast::channel(1).0, None),
             })
         }
     }
@@ -187,68 +585,153 @@ impl Peripheral {
         .filter(|c| c.uuid == WRITE_READ_NOTIFY_UUID || c.uuid == PNP_ID_UUID ).count() != 2{
             bail!(Error::NonSupport)
         }
-        let id: PeripheralId = device.id();
-        let mut slice = [0u8; 16];
-        slice[..6].clone_from_slice(&id.0.into_inner());
-        let uniid = Uuid::from_bytes(slice);
+        let uniid = ble_uuid(&device.id());
 
         let (sender,_) = broadcast::channel(5);
-        let ble = device.clone();
-        let send = sender.clone();
-
-        let thread_handle = tokio::spawn(async move{
-            // 订阅notify返回
-            if let Err(e) = ble.subscribe_by_uuid(&SERVICE_UUID,&WRITE_READ_NOTIFY_UUID).await{
-                println!("subscribe error:{}",e);
-            }
-            if let Ok(mut stream) = ble.notifications().await{
-                // Process while the BLE connection is not broken or stopped.
-                while let Some(data) = stream.next().await {
-                    // println!("recv data:{:?}",data.value);
-                    if let Err(e) = send.send(data.value){
-                        println!("send error:{}",e);
-                        break;
-                    }
-                }
-            }
-        });
+        let thread_handle = spawn_notify(device.clone(), sender.clone());
 
         // get device info
-        let pnp = device.read_by_uuid(&DEVICE_INFO_SERVICE_UUID, &PNP_ID_UUID).await?;
-        let firmware_revision = device.read_by_uuid(&DEVICE_INFO_SERVICE_UUID, &FIRMWARE_REVISION_UUID).await?;
-        let hardware_revision = device.read_by_uuid(&DEVICE_INFO_SERVICE_UUID, &HARDWARE_REVISION_UUID).await?;
-        let software_revision = device.read_by_uuid(&DEVICE_INFO_SERVICE_UUID, &SOFTWARE_REVISION_UUID).await?;
-        let vid:u16 = ((pnp[2] as u16) << 8) | pnp[1] as u16;
-        let pid:u16 = ((pnp[4] as u16) << 8) | pnp[3] as u16;
-
-        let mut name = "default".to_string();
-        if let Ok(propert) = device.properties().await {
-            name = propert.unwrap_or_default().local_name.unwrap_or_default();
-            println!("ble name:{}",name);
-        }
+        let info = Info::from_ble(&device).await?;
 
         Ok(Peripheral {
             shared: Arc::new(Shared {
                 id:uniid,
-                vid:vid,
-                pid:pid,
-                address:device.address().to_string(),
-                chip_manufacturer: ChipManufacturer::PAR,
-                device_type: DeviceType::MulKeyboardTouchpad,
-                device_name: name,
-                chip_type: ChipType::PAR2860,
-                software_version:String::from_utf8(firmware_revision).unwrap_or_default(),
-                hardware_version: String::from_utf8(hardware_revision).unwrap_or_default(),
-                firmware_version: String::from_utf8(software_revision).unwrap_or_default(),
+                address:RwLock::new(device.address().to_string()),
+                identity: ble_identity(&device.address()),
+                info: RwLock::new(info),
+                announcer: RwLock::new(None),
+                profiles: RwLock::new(None),
+                profile: RwLock::new(None),
                 /// 外围设备 
-                peripheral_device: PeripheralDevice{
-                    device:Device::Ble(device),
-                    sender:sender,
-                    _thread_handle:Some(thread_handle),
-                },
+                peripheral_device: PeripheralDevice::new(Device::Ble(device), None, sender, Some(thread_handle)),
             })
         })
     }
+
+    /// 重新向设备读取信息（USB 为描述字符串和 bcdDevice 版本，BLE 为 Device Information Service），
+    /// 有变化时发送 DeviceUpdated 事件，返回变化的字段
+    pub async fn refresh_info(&self) -> Result<Vec<InfoChange>> {
+        let device = &self.shared.peripheral_device;
+        device.check_connected()?;
+        let mut info = match &device.device() {
+            Device::Usb(_) => device.with_usb(Info::query_usb).await?,
+            Device::Ble(ble) => Info::from_ble(ble).await?,
+        };
+        // 固件升级后可能匹配到不同的型号，按新信息重新查找
+        let profile = self.shared.profiles.read().unwrap().as_ref()
+            .and_then(|profiles| profiles.lookup(&self.snapshot(&info)).cloned());
+        if let Some(profile) = &profile {
+            info.apply(profile);
+        }
+        let changes = {
+            let mut current = self.shared.info.write().unwrap();
+            let changes = current.diff(&info);
+            *current = info;
+            changes
+        };
+        *self.shared.profile.write().unwrap() = profile;
+        if !changes.is_empty() {
+            if let Some(announcer) = self.shared.announcer.read().unwrap().as_ref() {
+                let _ = announcer.send(CoreEvent::new(self.conn_type(), EventKind::DeviceUpdated(self.id(), changes.clone())));
+            }
+        }
+        Ok(changes)
+    }
+
+    /// 底层传输对象
+    pub(crate) fn device(&self) -> Device {
+        self.shared.peripheral_device.device()
+    }
+
+    /// 去抖使用的设备标识
+    pub(crate) fn identity(&self) -> &str {
+        &self.shared.identity
+    }
+
+    /// 窗口期内重新插入的 USB 设备，沿用原 Peripheral，替换失效的设备句柄
+    pub(crate) fn replace_usb(&self, device: UsbPeripheral) {
+        *self.shared.address.write().unwrap() = UsbDescriptor::from(&device).address();
+        self.shared.peripheral_device.replace_usb(device);
+    }
+
+    /// 设备加入 App 后，设置事件广播，并将 notify 数据转发为 Notification 事件
+    pub(crate) fn attach(&self, announcer: Sender<CoreEvent>) {
+        if let Device::Ble(_) = self.device() {
+            let id = self.id();
+            let mut rece = self.shared.peripheral_device.sender.subscribe();
+            let notify = announcer.clone();
+            tokio::spawn(async move {
+                loop {
+                    match rece.recv().await {
+                        Ok(data) => {
+                            let _ = notify.send(CoreEvent::new(ConnectionType::BLE, EventKind::Notification(id, data)));
+                        },
+                        Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                        Err(broadcast::error::RecvError::Closed) => break,
+                    }
+                }
+            });
+        }
+        *self.shared.announcer.write().unwrap() = Some(announcer);
+    }
+
+    /// 设置 BLE request 等待应答的超时，设备加入 App 前调用
+    pub(crate) fn set_request_timeout(&self, timeout: Duration) {
+        *self.shared.peripheral_device.request_timeout.write().unwrap() = timeout;
+    }
+
+    /// 设置型号表并按当前设备信息匹配型号，设备加入 App 前调用
+    pub(crate) fn set_profiles(&self, profiles: Arc<ProfileRegistry>) {
+        if let Some(profile) = profiles.lookup(&self.info()).cloned() {
+            self.shared.info.write().unwrap().apply(&profile);
+            *self.shared.profile.write().unwrap() = Some(profile);
+        }
+        *self.shared.profiles.write().unwrap() = Some(profiles);
+    }
+
+    /// 型号表中匹配的型号，未匹配时为 None
+    pub fn profile(&self) -> Option<DeviceProfile> {
+        self.shared.profile.read().unwrap().clone()
+    }
+
+    /// 当前设备信息的快照
+    pub fn info(&self) -> PeripheralInfo {
+        self.snapshot(&self.read_info())
+    }
+
+    fn snapshot(&self, info: &Info) -> PeripheralInfo {
+        PeripheralInfo {
+            id: self.shared.id,
+            conn_type: self.conn_type(),
+            address: self.address(),
+            vid: info.vid,
+            pid: info.pid,
+            chip_manufacturer: info.chip_manufacturer.clone(),
+            device_type: info.device_type.clone(),
+            device_name: info.device_name.clone(),
+            reported_name: info.reported_name.clone(),
+            chip_type: info.chip_type.clone(),
+            software_version: info.software_version.clone(),
+            hardware_version: info.hardware_version.clone(),
+            firmware_version: info.firmware_version.clone(),
+            serial_number: info.serial_number.clone(),
+            manufacturer: info.manufacturer.clone(),
+        }
+    }
+
+    fn read_info(&self) -> std::sync::RwLockReadGuard<'_, Info> {
+        self.shared.info.read().unwrap()
+    }
+
+    /// 连接意外断开，停止 notify 线程并标记为断开，等待重连
+    pub(crate) fn link_lost(&self) {
+        self.shared.peripheral_device.link_lost();
+    }
+
+    /// 是否由 disconnect 主动断开，主动断开的设备不自动重连
+    pub(crate) fn user_disconnected(&self) -> bool {
+        self.shared.peripheral_device.user_disconnected()
+    }
 }
 
 #[async_trait]
@@ -258,53 +741,64 @@ impl PeripheralApi for Peripheral {
     }
 
     fn address(&self) -> String {
-        self.shared.address.clone()
+        self.shared.address.read().unwrap().clone()
     }
 
     fn conn_type(&self) -> ConnectionType{
-        match &self.shared.peripheral_device.device {
+        match *self.shared.peripheral_device.device.read().unwrap() {
             Device::Usb(_) => ConnectionType::USB,
             Device::Ble(_) => ConnectionType::BLE,
         }
     }
 
     fn vendor_id(&self) -> u16 {
-        self.shared.vid
+        self.read_info().vid
     }
 
     fn product_id(&self) -> u16 {
-        self.shared.pid
+        self.read_info().pid
     }
 
     fn chip_manufacturer(&self) -> ChipManufacturer {
-        self.shared.chip_manufacturer.clone()
+        self.read_info().chip_manufacturer.clone()
     }
 
     fn device_type(&self) -> DeviceType {
-        self.shared.device_type.clone()
+        self.read_info().device_type.clone()
     }
 
     fn device_name(&self) -> String {
-        self.shared.device_name.clone()
+        self.read_info().device_name.clone()
     }
 
     fn chip_type(&self) -> ChipType {
-        self.shared.chip_type.clone()
+        self.read_info().chip_type.clone()
     }
 
-    fn software_version(&self) -> String {
-        self.shared.software_version.clone()
+    fn software_version(&self) -> Version {
+        self.read_info().software_version.clone()
     }
 
-    fn hardware_version(&self) -> String {
-        self.shared.hardware_version.clone()
+    fn hardware_version(&self) -> Version {
+        self.read_info().hardware_version.clone()
     }
 
-    fn firmware_version(&self) -> String {
-        self.shared.firmware_version.clone()
+    fn firmware_version(&self) -> Version {
+        self.read_info().firmware_version.clone()
     }
-    async fn connect(&self,_u:uuid::Uuid) ->  Result<()>  {
-        Ok(())
+    fn serial_number(&self) -> String {
+        self.read_info().serial_number.clone()
+    }
+
+    fn state(&self) -> PeripheralState {
+        self.shared.peripheral_device.state()
+    }
+
+    async fn connect(&self,u:uuid::Uuid) ->  Result<()>  {
+        if u != self.shared.id {
+            bail!(Error::DeviceNotFound)
+        }
+        self.shared.peripheral_device.connect().await
     }
 
     async fn read<'a>(&'a self,buf: &'a mut[u8])->  Result<usize>  {
@@ -319,7 +813,56 @@ impl PeripheralApi for Peripheral {
         self.shared.peripheral_device.request(src).await
     }
 
-    async fn disconnect(&mut self) ->  Result<()>  {
-        Ok(())
+    async fn disconnect(&self) ->  Result<()>  {
+        self.shared.peripheral_device.disconnect().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn link_state() {
+        assert_eq!(Link::Connected.state(), PeripheralState::Connected);
+        // 意外断开后可重连
+        assert_eq!(Link::Connected.lost(), Link::Lost);
+        assert_eq!(Link::Lost.state(), PeripheralState::Disconnected);
+        // 主动断开后收到断开事件仍视为主动断开
+        assert_eq!(Link::Closed.lost(), Link::Closed);
+        assert_eq!(Link::Closed.state(), PeripheralState::Disconnected);
+    }
+
+    #[test]
+    fn apply_keeps_reported_name() {
+        let mut info = Info {
+            vid: 0x3373,
+            pid: 0x0010,
+            chip_manufacturer: ChipManufacturer::Unknown(None),
+            device_type: DeviceType::Other,
+            device_name: "K1-BLE".to_string(),
+            reported_name: "K1-BLE".to_string(),
+            chip_type: ChipType::Unknown,
+            software_version: Version::default(),
+            hardware_version: Version::default(),
+            firmware_version: Version::default(),
+            serial_number: String::new(),
+            manufacturer: String::new(),
+        };
+        info.apply(&DeviceProfile {
+            chip_manufacturer: ChipManufacturer::JL,
+            chip_type: ChipType::AC635N,
+            device_type: DeviceType::Keyboard,
+            display_name: Some("K1 键盘".to_string()),
+            codec: None,
+        });
+        assert_eq!(info.device_name, "K1 键盘");
+        assert_eq!(info.reported_name, "K1-BLE");
+    }
+
+    #[test]
+    fn bcd_release_number() {
+        assert_eq!(bcd_version(0x0102).to_string(), "1.0.2");
+        assert_eq!(bcd_version(0x1234).to_string(), "12.3.4");
     }
 }
@@ -0,0 +1,42 @@
+use std::time::Duration;
+
+use anyhow::Result;
+use async_trait::async_trait;
+
+use crate::{
+    api::PeripheralApi,
+    peripheral::Peripheral,
+};
+
+/// 探测过滤器，在设备通过过滤之后、身份认证之前执行
+///
+/// device 为临时句柄，尚未加入 App，不会发送任何事件，拒绝后直接丢弃；
+/// budget 为允许的探测时间，超时或出错均视为拒绝。
+#[async_trait]
+pub trait ProbeFilter: Send + Sync {
+    async fn probe(&self, device: &Peripheral, budget: Duration) -> Result<bool>;
+}
+
+/// 发送固定探测命令，应答以指定前缀开头则接受
+#[derive(Debug, Clone)]
+pub struct CommandProbe {
+    command: Vec<u8>,
+    expect: Vec<u8>,
+}
+
+impl CommandProbe {
+    pub fn new(command: &[u8], expect: &[u8]) -> Self {
+        CommandProbe {
+            command: command.to_vec(),
+            expect: expect.to_vec(),
+        }
+    }
+}
+
+#[async_trait]
+impl ProbeFilter for CommandProbe {
+    async fn probe(&self, device: &Peripheral, _budget: Duration) -> Result<bool> {
+        let response = device.request(&self.command).await?;
+        Ok(response.starts_with(&self.expect))
+    }
+}
@@ -0,0 +1,320 @@
+use std::str::FromStr;
+
+use anyhow::{Result, anyhow};
+use regex::Regex;
+
+use crate::{
+    enums::{ChipManufacturer, ChipType, ConnectionType, DeviceType},
+    peripheral::PeripheralInfo,
+    version::VersionReq,
+};
+
+/// 设备型号信息，覆盖传输层默认识别的芯片、设备类型和名称
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeviceProfile {
+    pub chip_manufacturer: ChipManufacturer,
+    pub chip_type: ChipType,
+    pub device_type: DeviceType,
+    /// 显示名称，None 时使用设备上报的名称
+    pub display_name: Option<String>,
+    /// 协议编解码器标识，由上层按标识选择实现
+    pub codec: Option<String>,
+}
+
+/// 已有产品的 VID，内置型号表按此识别
+const BUILTIN_VID: u16 = 0x3373;
+
+/// 型号匹配条件，VID 必须一致，其余条件设置时才检查
+#[derive(Debug, Clone)]
+pub struct ProfileMatch {
+    vid: u16,
+    pid: Option<u16>,
+    /// 连接类型
+    conn_type: Option<ConnectionType>,
+    /// BLE 名称正则
+    name: Option<Regex>,
+    /// 固件版本范围
+    firmware: Option<VersionReq>,
+}
+
+impl ProfileMatch {
+    pub fn vid(vid: u16) -> Self {
+        ProfileMatch {
+            vid,
+            pid: None,
+            conn_type: None,
+            name: None,
+            firmware: None,
+        }
+    }
+
+    pub fn vid_pid(vid: u16, pid: u16) -> Self {
+        ProfileMatch {
+            pid: Some(pid),
+            ..Self::vid(vid)
+        }
+    }
+
+    /// 只匹配某个连接类型的设备
+    pub fn connection(mut self, conn_type: ConnectionType) -> Self {
+        self.conn_type = Some(conn_type);
+        self
+    }
+
+    /// 设备上报名称的正则，如 `^K1 `，不匹配型号表的显示名称
+    pub fn name_pattern(mut self, pattern: &str) -> Result<Self> {
+        self.name = Some(Regex::new(pattern)?);
+        Ok(self)
+    }
+
+    /// 固件版本范围，如 `">=1.2, <2.0"`
+    pub fn firmware(mut self, req: &str) -> Result<Self> {
+        self.firmware = Some(VersionReq::parse(req)?);
+        Ok(self)
+    }
+
+    fn matches(&self, info: &PeripheralInfo) -> bool {
+        info.vid == self.vid
+            && self.pid.map_or(true, |pid| info.pid == pid)
+            && self.conn_type.map_or(true, |conn_type| info.conn_type == conn_type)
+            && self.name.as_ref().map_or(true, |re| re.is_match(&info.reported_name))
+            && self.firmware.as_ref().map_or(true, |req| req.matches(&info.firmware_version))
+    }
+
+    /// 条件越多越优先
+    fn specificity(&self) -> usize {
+        [self.pid.is_some(), self.conn_type.is_some(), self.name.is_some(), self.firmware.is_some()].iter().filter(|b| **b).count()
+    }
+}
+
+/// 型号表，新增 SKU 只需注册或在数据文件中添加一条
+#[derive(Debug, Clone, Default)]
+pub struct ProfileRegistry {
+    entries: Vec<(ProfileMatch, DeviceProfile)>,
+}
+
+impl ProfileRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 内置型号表：已有产品 USB 连接为杰理 AC635N，BLE 连接为原相 PAR2860，均为键盘触摸板复合设备。
+    /// 未设置型号表时使用
+    pub fn builtin() -> Self {
+        let profile = |chip_manufacturer, chip_type| DeviceProfile {
+            chip_manufacturer,
+            chip_type,
+            device_type: DeviceType::MulKeyboardTouchpad,
+            display_name: None,
+            codec: None,
+        };
+        Self::new()
+            .register(ProfileMatch::vid(BUILTIN_VID).connection(ConnectionType::USB), profile(ChipManufacturer::JL, ChipType::AC635N))
+            .register(ProfileMatch::vid(BUILTIN_VID).connection(ConnectionType::BLE), profile(ChipManufacturer::PAR, ChipType::PAR2860))
+    }
+
+    pub fn register(mut self, matcher: ProfileMatch, profile: DeviceProfile) -> Self {
+        self.entries.push((matcher, profile));
+        self
+    }
+
+    /// 追加另一个型号表的型号，如在内置型号表上加入数据文件中的型号
+    pub fn extend(mut self, other: ProfileRegistry) -> Self {
+        self.entries.extend(other.entries);
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// 查找最匹配的型号，条件数相同时先注册的优先
+    pub fn lookup(&self, info: &PeripheralInfo) -> Option<&DeviceProfile> {
+        self.entries.iter()
+            .filter(|(m, _)| m.matches(info))
+            .fold(None, |best: Option<&(ProfileMatch, DeviceProfile)>, e| match best {
+                Some(b) if b.0.specificity() >= e.0.specificity() => Some(b),
+                _ => Some(e),
+            })
+            .map(|(_, p)| p)
+    }
+}
+
+#[cfg(feature = "config")]
+mod file {
+    use std::path::Path;
+
+    use serde::Deserialize;
+
+    use super::*;
+    use crate::config::ConfigFormat;
+
+    /// 数据文件中的一条型号，芯片和类型按名称解析
+    #[derive(Debug, Deserialize)]
+    #[serde(deny_unknown_fields)]
+    struct ProfileEntry {
+        vid: u16,
+        pid: Option<u16>,
+        /// `USB` 或 `BLE`
+        connection: Option<String>,
+        name: Option<String>,
+        firmware: Option<String>,
+        chip_manufacturer: String,
+        chip_type: String,
+        device_type: String,
+        display_name: Option<String>,
+        codec: Option<String>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    #[serde(deny_unknown_fields)]
+    struct ProfileFile {
+        #[serde(default)]
+        profile: Vec<ProfileEntry>,
+    }
+
+    fn parse<T: FromStr>(i: usize, key: &str, value: &str) -> Result<T> {
+        T::from_str(value).map_err(|_| anyhow!("invalid profile at `profile[{}].{}`: unknown {:?}", i, key, value))
+    }
+
+    impl ProfileRegistry {
+        /// 从 TOML/JSON 数据文件加载，格式按扩展名判断
+        pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+            let path = path.as_ref();
+            let format = ConfigFormat::from_path(path)?;
+            let content = std::fs::read_to_string(path).map_err(|e| anyhow!("{}: {}", path.display(), e))?;
+            Self::from_str(&content, format)
+        }
+
+        /// 从 TOML/JSON 字符串加载，根节点为 `profile` 数组
+        pub fn from_str(content: &str, format: ConfigFormat) -> Result<Self> {
+            let file: ProfileFile = match format {
+                ConfigFormat::Toml => serde_path_to_error::deserialize(toml::Deserializer::new(content))
+                    .map_err(|e| anyhow!("invalid profile at `{}`: {}", e.path(), e.inner()))?,
+                ConfigFormat::Json => serde_path_to_error::deserialize(&mut serde_json::Deserializer::from_str(content))
+                    .map_err(|e| anyhow!("invalid profile at `{}`: {}", e.path(), e.inner()))?,
+            };
+            let mut registry = ProfileRegistry::new();
+            for (i, entry) in file.profile.into_iter().enumerate() {
+                let mut matcher = match entry.pid {
+                    Some(pid) => ProfileMatch::vid_pid(entry.vid, pid),
+                    None => ProfileMatch::vid(entry.vid),
+                };
+                if let Some(connection) = &entry.connection {
+                    matcher = matcher.connection(parse(i, "connection", &connection.to_ascii_uppercase())?);
+                }
+                if let Some(name) = &entry.name {
+                    matcher = matcher.name_pattern(name).map_err(|e| anyhow!("invalid profile at `profile[{}].name`: {}", i, e))?;
+                }
+                if let Some(firmware) = &entry.firmware {
+                    matcher = matcher.firmware(firmware).map_err(|e| anyhow!("invalid profile at `profile[{}].firmware`: {}", i, e))?;
+                }
+                registry = registry.register(matcher, DeviceProfile {
+                    chip_manufacturer: parse(i, "chip_manufacturer", &entry.chip_manufacturer)?,
+                    chip_type: parse(i, "chip_type", &entry.chip_type)?,
+                    device_type: parse(i, "device_type", &entry.device_type)?,
+                    display_name: entry.display_name,
+                    codec: entry.codec,
+                });
+            }
+            Ok(registry)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::version::Version;
+
+    fn info(pid: u16, name: &str, firmware: &str) -> PeripheralInfo {
+        PeripheralInfo {
+            id: Uuid::nil(),
+            conn_type: ConnectionType::BLE,
+            address: String::new(),
+            vid: 0x3373,
+            pid,
+            chip_manufacturer: ChipManufacturer::default(),
+            device_type: DeviceType::default(),
+            device_name: name.to_string(),
+            reported_name: name.to_string(),
+            chip_type: ChipType::default(),
+            software_version: Version::default(),
+            hardware_version: Version::default(),
+            firmware_version: Version::from_raw(firmware),
+            serial_number: String::new(),
+            manufacturer: String::new(),
+        }
+    }
+
+    fn profile(device_type: DeviceType) -> DeviceProfile {
+        DeviceProfile {
+            chip_manufacturer: ChipManufacturer::JL,
+            chip_type: ChipType::AC632N,
+            device_type,
+            display_name: None,
+            codec: None,
+        }
+    }
+
+    #[test]
+    fn most_specific_wins() {
+        let registry = ProfileRegistry::new()
+            .register(ProfileMatch::vid(0x3373), profile(DeviceType::Other))
+            .register(ProfileMatch::vid_pid(0x3373, 0x0010), profile(DeviceType::Keyboard))
+            .register(ProfileMatch::vid_pid(0x3373, 0x0010).firmware(">=2.0").unwrap(), profile(DeviceType::MulKeyboardTouchpad));
+
+        assert_eq!(registry.lookup(&info(0x0010, "K1", "1.5")).unwrap().device_type, DeviceType::Keyboard);
+        assert_eq!(registry.lookup(&info(0x0010, "K1", "2.1")).unwrap().device_type, DeviceType::MulKeyboardTouchpad);
+        assert_eq!(registry.lookup(&info(0x0020, "M1", "1.0")).unwrap().device_type, DeviceType::Other);
+        assert!(ProfileRegistry::new().lookup(&info(0x0010, "K1", "1.0")).is_none());
+    }
+
+    #[test]
+    fn builtin_profiles() {
+        let registry = ProfileRegistry::builtin();
+        let ble = registry.lookup(&info(0x0010, "K1", "1.0")).unwrap();
+        assert_eq!((&ble.chip_manufacturer, &ble.chip_type), (&ChipManufacturer::PAR, &ChipType::PAR2860));
+        assert_eq!(ble.device_type, DeviceType::MulKeyboardTouchpad);
+
+        let usb = PeripheralInfo { conn_type: ConnectionType::USB, ..info(0x0010, "K1", "1.0") };
+        assert_eq!(registry.lookup(&usb).unwrap().chip_type, ChipType::AC635N);
+
+        // 未知厂商的设备如实报告为未识别
+        let other = PeripheralInfo { vid: 0x1234, ..info(0x0010, "K1", "1.0") };
+        assert!(registry.lookup(&other).is_none());
+
+        // 追加的型号条件更多时优先
+        let registry = registry.register(ProfileMatch::vid_pid(0x3373, 0x0010).connection(ConnectionType::BLE), profile(DeviceType::Keyboard));
+        assert_eq!(registry.lookup(&info(0x0010, "K1", "1.0")).unwrap().device_type, DeviceType::Keyboard);
+    }
+
+    #[cfg(feature = "config")]
+    #[test]
+    fn load_data_file() {
+        use crate::config::ConfigFormat;
+
+        let toml = r#"
+            [[profile]]
+            vid = 0x3373
+            pid = 0x0010
+            name = "^K1"
+            chip_manufacturer = "JL"
+            chip_type = "AC635N"
+            device_type = "Keyboard"
+            codec = "lc-v1"
+        "#;
+        let registry = ProfileRegistry::from_str(toml, ConfigFormat::Toml).unwrap();
+        let found = registry.lookup(&info(0x0010, "K1 Pro", "1.0")).unwrap();
+        assert_eq!(found.chip_type, ChipType::AC635N);
+        assert_eq!(found.codec.as_deref(), Some("lc-v1"));
+
+        let err = ProfileRegistry::from_str(&toml.replace("AC635N", "XYZ"), ConfigFormat::Toml).unwrap_err();
+        assert!(err.to_string().contains("profile[0].chip_type"), "{}", err);
+
+        let err = ProfileRegistry::from_str(&toml.replace("\"Keyboard\"", "\"Keybord\""), ConfigFormat::Toml).unwrap_err();
+        assert!(err.to_string().contains("profile[0].device_type"), "{}", err);
+    }
+}
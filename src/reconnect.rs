@@ -0,0 +1,159 @@
+use std::{sync::Arc, time::Duration};
+
+use tokio::{sync::broadcast::Sender, time};
+use uuid::Uuid;
+
+use crate::{
+    api::PeripheralApi,
+    enums::{ConnectionType, CoreEvent, EventKind, PeripheralState, RemoveReason},
+    peripheral::Peripheral,
+    registry::Registry,
+    tasks::KeyedTasks,
+};
+
+/// BLE 自动重连配置，重连间隔按指数退避
+#[derive(Debug, Clone)]
+pub struct ReconnectOptions {
+    /// 最大重试次数，超过后发送 DeviceRemove
+    pub max_retries: u32,
+    /// 首次重连间隔
+    pub initial_delay: Duration,
+    /// 最大重连间隔
+    pub max_delay: Duration,
+}
+
+impl Default for ReconnectOptions {
+    fn default() -> Self {
+        ReconnectOptions {
+            max_retries: 10,
+            initial_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+        }
+    }
+}
+
+impl ReconnectOptions {
+    /// 第 attempt 次（从 1 开始）重连前的等待时间
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let factor = 1u32.checked_shl(attempt.saturating_sub(1)).unwrap_or(u32::MAX);
+        self.initial_delay.saturating_mul(factor).min(self.max_delay)
+    }
+}
+
+/// BLE 连接断开后的处理
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum DisconnectAction {
+    /// 用户主动断开，保留设备等待 connect
+    Keep,
+    /// 按退避间隔自动重连
+    Reconnect,
+    /// 移除设备
+    Remove,
+}
+
+impl DisconnectAction {
+    pub(crate) fn new(user_disconnected: bool, auto_reconnect: bool) -> Self {
+        if user_disconnected {
+            DisconnectAction::Keep
+        } else if auto_reconnect {
+            DisconnectAction::Reconnect
+        } else {
+            DisconnectAction::Remove
+        }
+    }
+}
+
+/// 管理进行中的重连任务，重连期间保留原 Peripheral 对象
+#[derive(Default)]
+pub(crate) struct Reconnector {
+    tasks: KeyedTasks<Uuid>,
+}
+
+impl Reconnector {
+    /// 连接断开，开始按退避间隔重连，已在重连的设备忽略
+    pub(crate) fn start(
+        &self,
+        peripheral: Peripheral,
+        options: ReconnectOptions,
+        registry: Arc<Registry>,
+        sender: Sender<CoreEvent>,
+    ) {
+        let id = peripheral.id();
+        // 已重连成功但任务尚未结束时，再次断开需要重新开始
+        if self.tasks.contains(&id) && peripheral.state() != PeripheralState::Connected {
+            return;
+        }
+        peripheral.link_lost();
+        self.tasks.spawn_replace(id, async move {
+            let _ = sender.send(CoreEvent::new(ConnectionType::BLE, EventKind::ConnectionLost(id)));
+            for attempt in 1..=options.max_retries {
+                let _ = sender.send(CoreEvent::new(ConnectionType::BLE, EventKind::Reconnecting(id, attempt)));
+                time::sleep(options.delay(attempt)).await;
+                // 重连期间用户主动断开，停止重连
+                if peripheral.user_disconnected() {
+                    return;
+                }
+                match peripheral.connect(id).await {
+                    Ok(_) => {
+                        let _ = sender.send(CoreEvent::new(ConnectionType::BLE, EventKind::Reconnected(id)));
+                        return;
+                    },
+                    Err(e) => println!("reconnect {} error:{:?}", id, e),
+                }
+            }
+            registry.remove(&id, RemoveReason::ReconnectFailed, &sender);
+        });
+    }
+
+    /// 系统已重新连接设备，取消退避等待，立即重新订阅，失败则继续退避重连
+    pub(crate) async fn resume(
+        &self,
+        peripheral: &Peripheral,
+        options: ReconnectOptions,
+        registry: Arc<Registry>,
+        sender: Sender<CoreEvent>,
+    ) {
+        let id = peripheral.id();
+        let pending = self.tasks.cancel(&id);
+        if !pending && peripheral.state() == PeripheralState::Connected {
+            return;
+        }
+        match peripheral.connect(id).await {
+            Ok(_) => {
+                let _ = sender.send(CoreEvent::new(ConnectionType::BLE, EventKind::Reconnected(id)));
+            },
+            Err(e) => {
+                println!("reconnect {} error:{:?}", id, e);
+                self.start(peripheral.clone(), options, registry, sender);
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_delay() {
+        let options = ReconnectOptions {
+            max_retries: 10,
+            initial_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(1),
+        };
+        assert_eq!(options.delay(1), Duration::from_millis(100));
+        assert_eq!(options.delay(2), Duration::from_millis(200));
+        assert_eq!(options.delay(4), Duration::from_millis(800));
+        assert_eq!(options.delay(5), Duration::from_secs(1));
+        assert_eq!(options.delay(64), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn disconnect_action() {
+        assert_eq!(DisconnectAction::new(false, true), DisconnectAction::Reconnect);
+        assert_eq!(DisconnectAction::new(false, false), DisconnectAction::Remove);
+        // 用户主动断开的设备不自动重连，也不移除
+        assert_eq!(DisconnectAction::new(true, true), DisconnectAction::Keep);
+        assert_eq!(DisconnectAction::new(true, false), DisconnectAction::Keep);
+    }
+}
@@ -0,0 +1,82 @@
+use std::sync::Mutex;
+
+use dashmap::DashMap;
+use tokio::sync::broadcast::{Receiver, Sender};
+use uuid::Uuid;
+
+use crate::{
+    api::PeripheralApi,
+    enums::{ConnectionType, CoreEvent, EventKind, RemoveReason},
+    logical::{LogicalDevice, LogicalRegistry},
+    peripheral::Peripheral,
+};
+
+/// 已加入的设备集合
+///
+/// 设备的加入/移除与对应事件的发送在同一把锁内完成，
+/// 保证 `subscribe` 得到的快照和事件流之间不会遗漏或重复设备。
+pub(crate) struct Registry {
+    devices: DashMap<Uuid, Peripheral>,
+    /// 同一物理设备的多个连接
+    logical: LogicalRegistry,
+    lock: Mutex<()>,
+}
+
+impl Registry {
+    pub(crate) fn new(preferred: ConnectionType) -> Self {
+        Registry {
+            devices: DashMap::new(),
+            logical: LogicalRegistry::new(preferred),
+            lock: Mutex::new(()),
+        }
+    }
+
+    pub(crate) fn get(&self, id: &Uuid) -> Option<Peripheral> {
+        self.devices.get(id).map(|p| p.clone())
+    }
+
+    pub(crate) fn find_by_address(&self, address: &str) -> Option<Peripheral> {
+        self.devices.iter().find(|p| p.address() == address).map(|p| p.clone())
+    }
+
+    pub(crate) fn find_by_identity(&self, identity: &str) -> Option<Peripheral> {
+        self.devices.iter().find(|p| p.identity() == identity).map(|p| p.clone())
+    }
+
+    pub(crate) fn list(&self) -> Vec<Peripheral> {
+        self.devices.iter().map(|p| p.clone()).collect()
+    }
+
+    pub(crate) fn logical_device(&self, key: &str) -> Option<LogicalDevice> {
+        self.logical.get(key)
+    }
+
+    pub(crate) fn logical_devices(&self) -> Vec<LogicalDevice> {
+        self.logical.list()
+    }
+
+    /// 加入设备并发送 DeviceAdd，key 为逻辑设备标识
+    pub(crate) fn add(&self, peripheral: Peripheral, key: Option<String>, sender: &Sender<CoreEvent>) {
+        let _guard = self.lock.lock().unwrap();
+        self.devices.insert(peripheral.id(), peripheral.clone());
+        if let Some(key) = key {
+            self.logical.attach(key, &peripheral);
+        }
+        let _ = sender.send(CoreEvent::new(peripheral.conn_type(), EventKind::DeviceAdd(peripheral)));
+    }
+
+    /// 移除设备并发送 DeviceRemove，设备不存在时返回 None
+    pub(crate) fn remove(&self, id: &Uuid, reason: RemoveReason, sender: &Sender<CoreEvent>) -> Option<Peripheral> {
+        let _guard = self.lock.lock().unwrap();
+        let (_, peripheral) = self.devices.remove(id)?;
+        self.logical.detach(id);
+        let _ = sender.send(CoreEvent::new(peripheral.conn_type(), EventKind::DeviceRemove(peripheral.info(), reason)));
+        Some(peripheral)
+    }
+
+    /// 原子地获取当前设备快照并订阅之后的事件
+    pub(crate) fn subscribe(&self, sender: &Sender<CoreEvent>) -> (Vec<Peripheral>, Receiver<CoreEvent>) {
+        let _guard = self.lock.lock().unwrap();
+        (self.list(), sender.subscribe())
+    }
+}
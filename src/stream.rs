@@ -0,0 +1,220 @@
+use std::{
+    collections::VecDeque,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use futures::{Stream, ready};
+use tokio::sync::broadcast::Receiver;
+use tokio_stream::wrappers::{BroadcastStream, errors::BroadcastStreamRecvError};
+
+use crate::{
+    api::PeripheralApi,
+    enums::{AdapterState, ConnectionType, CoreEvent, DeviceType, EventKind},
+    matcher::DeviceMatcher,
+    peripheral::{Peripheral, PeripheralInfo},
+    registry::Registry,
+};
+
+/// 设备事件流，由 App::events 创建
+///
+/// 事件流落后时不返回错误，而是产生一个 `EventKind::Resync` 事件。
+/// 适配器事件只按连接类型过滤，Resync 事件不过滤。
+pub struct EventStream {
+    inner: BroadcastStream<CoreEvent>,
+    registry: Arc<Registry>,
+    matcher: DeviceMatcher,
+    /// 回放的事件，先于 inner 返回
+    pending: VecDeque<CoreEvent>,
+}
+
+impl EventStream {
+    pub(crate) fn new(receiver: Receiver<CoreEvent>, registry: Arc<Registry>) -> Self {
+        EventStream {
+            inner: BroadcastStream::new(receiver),
+            registry,
+            matcher: DeviceMatcher::default(),
+            pending: VecDeque::new(),
+        }
+    }
+
+    /// 在事件流之前回放这些适配器状态的 AdapterStateChanged
+    pub(crate) fn replay_adapter_states(mut self, states: Vec<(ConnectionType, AdapterState)>) -> Self {
+        self.pending.extend(states.into_iter()
+            .map(|(conn_type, state)| CoreEvent::new(conn_type, EventKind::AdapterStateChanged(state))));
+        self
+    }
+
+    /// 在事件流之前回放这些设备的 DeviceAdd
+    pub(crate) fn replay(mut self, peripherals: Vec<Peripheral>) -> Self {
+        self.pending.extend(peripherals.into_iter()
+            .map(|p| CoreEvent::new(p.conn_type(), EventKind::DeviceAdd(p))));
+        self
+    }
+
+    /// 只保留某个设备类型的事件
+    pub fn devices_of_type(mut self, device_type: DeviceType) -> Self {
+        self.matcher = self.matcher.device_type(device_type);
+        self
+    }
+
+    /// 只保留某个连接类型的事件
+    pub fn connection(mut self, conn_type: ConnectionType) -> Self {
+        self.matcher = self.matcher.connection(conn_type);
+        self
+    }
+
+    /// 只保留某个 VID/PID 设备的事件
+    pub fn vid_pid(mut self, vid: u16, pid: u16) -> Self {
+        self.matcher = self.matcher.vid_pid(vid, pid);
+        self
+    }
+
+    /// 使用自定义匹配条件，替换之前设置的条件
+    pub fn matching(mut self, matcher: DeviceMatcher) -> Self {
+        self.matcher = matcher;
+        self
+    }
+
+    /// 事件相关的设备信息，未加入的设备返回 None
+    fn event_info(&self, event: &CoreEvent) -> Option<PeripheralInfo> {
+        match &event.kind {
+            EventKind::DeviceAdd(peripheral) => Some(peripheral.info()),
+            EventKind::DeviceRemove(info, _) => Some(info.clone()),
+            _ => event.device_id()
+                .and_then(|id| self.registry.get(&id).map(|p| p.info())),
+        }
+    }
+
+    fn accept(&self, event: &CoreEvent) -> bool {
+        let conn_type = match event.conn_type {
+            Some(conn_type) => conn_type,
+            None => return true,
+        };
+        if !self.matcher.matches_connection(conn_type) {
+            return false;
+        }
+        if let EventKind::AdapterStateChanged(_) = event.kind {
+            return true;
+        }
+        match self.event_info(event) {
+            Some(info) => self.matcher.matches(&info),
+            None => !self.matcher.has_device_criteria(),
+        }
+    }
+}
+
+impl Stream for EventStream {
+    type Item = CoreEvent;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        while let Some(event) = self.pending.pop_front() {
+            if self.accept(&event) {
+                return Poll::Ready(Some(event));
+            }
+        }
+        loop {
+            match ready!(Pin::new(&mut self.inner).poll_next(cx)) {
+                Some(Ok(event)) => {
+                    if self.accept(&event) {
+                        return Poll::Ready(Some(event));
+                    }
+                },
+                Some(Err(BroadcastStreamRecvError::Lagged(missed))) => {
+                    return Poll::Ready(Some(CoreEvent::resync(missed)));
+                },
+                None => return Poll::Ready(None),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::StreamExt;
+    use tokio::sync::broadcast;
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::{
+        enums::RemoveReason,
+        matcher::tests::info,
+    };
+
+    fn remove(conn_type: ConnectionType, vid: u16, pid: u16) -> CoreEvent {
+        let info = info(conn_type, vid, pid, DeviceType::Keyboard);
+        CoreEvent::new(conn_type, EventKind::DeviceRemove(info, RemoveReason::Unplugged))
+    }
+
+    fn adapter(conn_type: ConnectionType) -> CoreEvent {
+        CoreEvent::new(conn_type, EventKind::AdapterStateChanged(AdapterState::PoweredOff))
+    }
+
+    /// 发送事件后关闭通道，收集事件流的全部事件
+    async fn collect(stream: impl FnOnce(EventStream) -> EventStream, capacity: usize, events: Vec<CoreEvent>) -> Vec<EventKind> {
+        let (sender, receiver) = broadcast::channel(capacity);
+        let stream = stream(EventStream::new(receiver, Arc::new(Registry::new(ConnectionType::USB))));
+        for event in events {
+            sender.send(event).unwrap();
+        }
+        drop(sender);
+        stream.map(|e| e.kind).collect().await
+    }
+
+    #[tokio::test]
+    async fn filter_by_device() {
+        let events = collect(|s| s.vid_pid(0x3373, 0x0001), 8, vec![
+            remove(ConnectionType::USB, 0x3373, 0x0001),
+            remove(ConnectionType::USB, 0x3373, 0x0002),
+            adapter(ConnectionType::BLE),
+            // 未加入的设备没有设备信息，设置了设备条件时丢弃
+            CoreEvent::new(ConnectionType::USB, EventKind::Notification(Uuid::new_v4(), vec![0x01])),
+            remove(ConnectionType::BLE, 0x3373, 0x0001),
+        ]).await;
+        assert_eq!(events.len(), 3);
+        assert!(matches!(&events[0], EventKind::DeviceRemove(info, _) if info.conn_type == ConnectionType::USB && info.pid == 0x0001));
+        assert!(matches!(events[1], EventKind::AdapterStateChanged(_)));
+        assert!(matches!(&events[2], EventKind::DeviceRemove(info, _) if info.conn_type == ConnectionType::BLE));
+    }
+
+    #[tokio::test]
+    async fn filter_by_connection() {
+        let events = collect(|s| s.connection(ConnectionType::USB), 8, vec![
+            adapter(ConnectionType::BLE),
+            remove(ConnectionType::BLE, 0x3373, 0x0001),
+            adapter(ConnectionType::USB),
+            CoreEvent::new(ConnectionType::USB, EventKind::Reconnected(Uuid::new_v4())),
+            CoreEvent::resync(1),
+        ]).await;
+        assert_eq!(events.len(), 3);
+        assert!(matches!(events[0], EventKind::AdapterStateChanged(_)));
+        assert!(matches!(events[1], EventKind::Reconnected(_)));
+        assert!(matches!(events[2], EventKind::Resync(1)));
+    }
+
+    #[tokio::test]
+    async fn replay_before_events() {
+        let states = vec![
+            (ConnectionType::USB, AdapterState::Available),
+            (ConnectionType::BLE, AdapterState::Unavailable("no radio".to_string())),
+        ];
+        let events = collect(|s| s.connection(ConnectionType::BLE).replay_adapter_states(states), 8, vec![
+            adapter(ConnectionType::BLE),
+        ]).await;
+        assert_eq!(events.len(), 2);
+        assert!(matches!(&events[0], EventKind::AdapterStateChanged(AdapterState::Unavailable(_))));
+        assert!(matches!(events[1], EventKind::AdapterStateChanged(AdapterState::PoweredOff)));
+    }
+
+    #[tokio::test]
+    async fn lag_becomes_resync() {
+        let events = (0..5).map(|pid| remove(ConnectionType::USB, 0x3373, pid)).collect();
+        let events = collect(|s| s, 2, events).await;
+        // 容量为 2，丢失最早的 3 个事件
+        assert_eq!(events.len(), 3);
+        assert!(matches!(events[0], EventKind::Resync(3)));
+        assert!(matches!(&events[1], EventKind::DeviceRemove(info, _) if info.pid == 3));
+        assert!(matches!(&events[2], EventKind::DeviceRemove(info, _) if info.pid == 4));
+    }
+}
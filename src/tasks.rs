@@ -0,0 +1,165 @@
+use std::{
+    future::Future,
+    hash::Hash,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+use dashmap::{mapref::entry::Entry, DashMap};
+use tokio::task::JoinHandle;
+
+/// 按 key 管理的后台任务，每个 key 最多一个任务
+///
+/// 任务句柄在启动时即登记，任务结束时注销。注销只移除自己登记的句柄，
+/// 不会影响之后替换的任务。
+pub(crate) struct KeyedTasks<K: Eq + Hash> {
+    tasks: Arc<DashMap<K, (u64, JoinHandle<()>)>>,
+    /// 任务代数，区分同一 key 先后启动的任务
+    generation: AtomicU64,
+}
+
+impl<K: Eq + Hash> Default for KeyedTasks<K> {
+    fn default() -> Self {
+        KeyedTasks {
+            tasks: Arc::new(DashMap::new()),
+            generation: AtomicU64::new(0),
+        }
+    }
+}
+
+impl<K> KeyedTasks<K>
+where
+    K: Eq + Hash + Clone + Send + Sync + 'static,
+{
+    /// 启动任务，结束时注销，返回登记的代数和句柄
+    fn spawn<F>(&self, key: K, future: F) -> (u64, JoinHandle<()>)
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        let generation = self.generation.fetch_add(1, Ordering::Relaxed);
+        let tasks = Arc::clone(&self.tasks);
+        let handle = tokio::spawn(async move {
+            future.await;
+            tasks.remove_if(&key, |_, (g, _)| *g == generation);
+        });
+        (generation, handle)
+    }
+
+    /// key 没有进行中的任务时启动，返回是否启动
+    pub(crate) fn spawn_if_absent<F>(&self, key: K, future: F) -> bool
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        match self.tasks.entry(key) {
+            Entry::Occupied(_) => false,
+            Entry::Vacant(entry) => {
+                // 持有 entry 时登记，任务即使立即结束也只能在登记之后注销
+                let task = self.spawn(entry.key().clone(), future);
+                entry.insert(task);
+                true
+            },
+        }
+    }
+
+    /// 启动任务，取消该 key 进行中的任务
+    pub(crate) fn spawn_replace<F>(&self, key: K, future: F)
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        let old = match self.tasks.entry(key) {
+            Entry::Occupied(mut entry) => {
+                let task = self.spawn(entry.key().clone(), future);
+                Some(entry.insert(task))
+            },
+            Entry::Vacant(entry) => {
+                let task = self.spawn(entry.key().clone(), future);
+                entry.insert(task);
+                None
+            },
+        };
+        if let Some((_, handle)) = old {
+            handle.abort();
+        }
+    }
+
+    /// 取消进行中的任务，返回是否有任务
+    pub(crate) fn cancel(&self, key: &K) -> bool {
+        match self.tasks.remove(key) {
+            Some((_, (_, handle))) => {
+                handle.abort();
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// 是否有进行中的任务
+    pub(crate) fn contains(&self, key: &K) -> bool {
+        self.tasks.contains_key(key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tokio::time;
+
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn finished_task_releases_key() {
+        let tasks = KeyedTasks::default();
+        // 立即结束的任务不会留下句柄
+        assert!(tasks.spawn_if_absent(1, async {}));
+        time::sleep(Duration::from_millis(1)).await;
+        assert!(!tasks.contains(&1));
+        assert!(tasks.spawn_if_absent(1, async {}));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn immediate_task_on_worker() {
+        let tasks = KeyedTasks::default();
+        for _ in 0..100 {
+            assert!(tasks.spawn_if_absent(1, async {}));
+            while tasks.contains(&1) {
+                tokio::task::yield_now().await;
+            }
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn running_task_blocks_key() {
+        let tasks = KeyedTasks::default();
+        assert!(tasks.spawn_if_absent(1, time::sleep(Duration::from_secs(1))));
+        assert!(!tasks.spawn_if_absent(1, async {}));
+        assert!(tasks.spawn_if_absent(2, async {}));
+
+        time::sleep(Duration::from_secs(2)).await;
+        assert!(!tasks.contains(&1));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn replaced_task_keeps_new_handle() {
+        let tasks = KeyedTasks::default();
+        tasks.spawn_replace(1, time::sleep(Duration::from_secs(1)));
+        tasks.spawn_replace(1, time::sleep(Duration::from_secs(3)));
+
+        // 旧任务已取消，不会注销新任务
+        time::sleep(Duration::from_secs(2)).await;
+        assert!(tasks.contains(&1));
+        time::sleep(Duration::from_secs(2)).await;
+        assert!(!tasks.contains(&1));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn cancel() {
+        let tasks = KeyedTasks::default();
+        tasks.spawn_if_absent(1, time::sleep(Duration::from_secs(1)));
+        assert!(tasks.cancel(&1));
+        assert!(!tasks.cancel(&1));
+        assert!(tasks.spawn_if_absent(1, async {}));
+    }
+}
@@ -0,0 +1,284 @@
+use std::{cmp::Ordering, fmt, str::FromStr};
+
+use anyhow::{Result, anyhow, bail};
+
+/// 设备版本号，保留设备上报的原始字符串
+///
+/// 兼容的格式：`1.4.0`、`V1.4`、`v1.04.2-beta`、`1.4.0_rc1`、`1.4.0 build 12`，
+/// 比较时忽略前缀 `v` 和空格/`+` 之后的构建信息，缺少的段按 0 处理（`1.4` == `1.4.0`），
+/// 带 `-`/`_` 后缀的预发布版本小于正式版本。
+#[derive(Debug, Clone, Default)]
+pub struct Version {
+    raw: String,
+    parts: Vec<u32>,
+    pre: Option<String>,
+}
+
+impl Version {
+    /// 严格解析，格式不正确时返回错误
+    pub fn parse(s: &str) -> Result<Self> {
+        let raw = s.trim_matches(|c: char| c == '\0' || c.is_whitespace());
+        let body = raw.strip_prefix(['v', 'V']).unwrap_or(raw);
+        // 构建信息不参与比较
+        let body = body.split(['+', ' ']).next().unwrap_or_default();
+        let (numbers, pre) = match body.find(['-', '_']) {
+            Some(i) => (&body[..i], Some(body[i + 1..].trim().to_string())),
+            None => (body, None),
+        };
+        if numbers.is_empty() {
+            bail!("invalid version: {:?}", s);
+        }
+        let parts = numbers.split('.')
+            .map(|p| p.parse::<u32>().map_err(|_| anyhow!("invalid version: {:?}", s)))
+            .collect::<Result<Vec<u32>>>()?;
+        Ok(Version {
+            raw: raw.to_string(),
+            parts,
+            pre: pre.filter(|p| !p.is_empty()),
+        })
+    }
+
+    /// 宽松解析，用于设备上报的字符串，无法解析时只保留原始字符串
+    pub fn from_raw(s: &str) -> Self {
+        Self::parse(s).unwrap_or_else(|_| Version {
+            raw: s.trim_matches(|c: char| c == '\0' || c.is_whitespace()).to_string(),
+            parts: Vec::new(),
+            pre: None,
+        })
+    }
+
+    /// 从 GATT 读取的字节解析
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        Self::from_raw(&String::from_utf8_lossy(bytes))
+    }
+
+    /// 设备上报的原始字符串
+    pub fn raw(&self) -> &str {
+        &self.raw
+    }
+
+    /// 是否为可比较的版本号
+    pub fn is_valid(&self) -> bool {
+        !self.parts.is_empty()
+    }
+
+    pub fn major(&self) -> u32 {
+        self.part(0)
+    }
+
+    pub fn minor(&self) -> u32 {
+        self.part(1)
+    }
+
+    pub fn patch(&self) -> u32 {
+        self.part(2)
+    }
+
+    /// 预发布后缀，如 `beta`
+    pub fn pre(&self) -> Option<&str> {
+        self.pre.as_deref()
+    }
+
+    /// 是否满足版本范围，如 `">=1.2.0, <2.0"`，范围格式错误时返回 false
+    pub fn matches(&self, req: &str) -> bool {
+        VersionReq::parse(req).map(|r| r.matches(self)).unwrap_or(false)
+    }
+
+    fn part(&self, i: usize) -> u32 {
+        self.parts.get(i).copied().unwrap_or(0)
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.raw)
+    }
+}
+
+impl FromStr for Version {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Self::parse(s)
+    }
+}
+
+impl Version {
+    /// 按版本号比较，调用方保证两者都可比较
+    fn cmp_parts(&self, other: &Self) -> Ordering {
+        let len = self.parts.len().max(other.parts.len());
+        for i in 0..len {
+            match self.part(i).cmp(&other.part(i)) {
+                Ordering::Equal => {},
+                o => return o,
+            }
+        }
+        match (&self.pre, &other.pre) {
+            (None, None) => Ordering::Equal,
+            (None, Some(_)) => Ordering::Greater,
+            (Some(_), None) => Ordering::Less,
+            (Some(a), Some(b)) => a.cmp(b),
+        }
+    }
+}
+
+/// 无法解析的版本号只与原始字符串相同的版本号相等，与其他版本号不可比较
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        match (self.is_valid(), other.is_valid()) {
+            (true, true) => Some(self.cmp_parts(other)),
+            (false, false) if self.raw == other.raw => Some(Ordering::Equal),
+            _ => None,
+        }
+    }
+}
+
+impl PartialEq for Version {
+    fn eq(&self, other: &Self) -> bool {
+        self.partial_cmp(other) == Some(Ordering::Equal)
+    }
+}
+
+impl Eq for Version {}
+
+/// 与 Version 之间的比较一致，字符串按 from_raw 解析
+impl PartialEq<&str> for Version {
+    fn eq(&self, other: &&str) -> bool {
+        *self == Version::from_raw(other)
+    }
+}
+
+impl PartialOrd<&str> for Version {
+    fn partial_cmp(&self, other: &&str) -> Option<Ordering> {
+        self.partial_cmp(&Version::from_raw(other))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Version {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.raw)
+    }
+}
+
+/// 按原始字符串宽松解析
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Version {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let raw = String::deserialize(deserializer)?;
+        Ok(Version::from_raw(&raw))
+    }
+}
+
+/// 版本范围，逗号分隔的多个条件同时满足，如 `">=1.2.0, <2.0"`
+#[derive(Debug, Clone)]
+pub struct VersionReq {
+    conditions: Vec<(Op, Version)>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Op {
+    Eq,
+    Gt,
+    Ge,
+    Lt,
+    Le,
+}
+
+impl VersionReq {
+    pub fn parse(s: &str) -> Result<Self> {
+        let mut conditions = Vec::new();
+        for cond in s.split(',').map(str::trim).filter(|c| !c.is_empty()) {
+            let (op, v) = if let Some(v) = cond.strip_prefix(">=") {
+                (Op::Ge, v)
+            } else if let Some(v) = cond.strip_prefix("<=") {
+                (Op::Le, v)
+            } else if let Some(v) = cond.strip_prefix('>') {
+                (Op::Gt, v)
+            } else if let Some(v) = cond.strip_prefix('<') {
+                (Op::Lt, v)
+            } else if let Some(v) = cond.strip_prefix('=') {
+                (Op::Eq, v)
+            } else {
+                (Op::Eq, cond)
+            };
+            conditions.push((op, Version::parse(v)?));
+        }
+        if conditions.is_empty() {
+            bail!("empty version requirement");
+        }
+        Ok(VersionReq { conditions })
+    }
+
+    /// 无法解析的版本号不满足任何范围
+    pub fn matches(&self, version: &Version) -> bool {
+        version.is_valid() && self.conditions.iter().all(|(op, v)| match op {
+            Op::Eq => version == v,
+            Op::Gt => version > v,
+            Op::Ge => version >= v,
+            Op::Lt => version < v,
+            Op::Le => version <= v,
+        })
+    }
+}
+
+impl FromStr for VersionReq {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Self::parse(s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_formats() {
+        let v = Version::parse("V1.04.2-beta").unwrap();
+        assert_eq!((v.major(), v.minor(), v.patch()), (1, 4, 2));
+        assert_eq!(v.pre(), Some("beta"));
+        assert_eq!(v.raw(), "V1.04.2-beta");
+
+        assert_eq!(Version::from_bytes(b"1.4.0\0\0").raw(), "1.4.0");
+        assert!(Version::parse("abc").is_err());
+        assert!(!Version::from_raw("abc").is_valid());
+        assert_eq!(Version::from_raw("abc").raw(), "abc");
+    }
+
+    #[test]
+    fn ordering() {
+        let v = Version::parse("1.4").unwrap();
+        assert!(v == "1.4.0");
+        assert!(v < "1.4.1");
+        assert!(v > "1.3.9");
+        assert!(Version::parse("1.4.0-rc1").unwrap() < "1.4.0");
+        assert!(Version::parse("1.4.0 build 12").unwrap() == "1.4.0");
+        assert_eq!(Version::from_raw("abc").partial_cmp(&"1.0"), None);
+    }
+
+    #[test]
+    fn invalid_ordering() {
+        let abc = Version::from_raw("abc");
+        let xyz = Version::from_raw("xyz");
+        let one = Version::parse("1.0").unwrap();
+        assert!(abc != xyz);
+        assert_eq!(abc.partial_cmp(&xyz), None);
+        assert_eq!(abc, Version::from_raw("abc"));
+        assert!(abc == "abc");
+        // 无法解析的版本号与有效版本号不可比较，两种比较方式一致
+        assert_eq!(abc.partial_cmp(&one), None);
+        assert_eq!(abc.partial_cmp(&"1.0"), None);
+        assert!(abc != one);
+    }
+
+    #[test]
+    fn range() {
+        let v = Version::parse("1.5.2").unwrap();
+        assert!(v.matches(">=1.4.0, <2.0"));
+        assert!(!v.matches(">1.5.2"));
+        assert!(v.matches("1.5.2"));
+        assert!(!v.matches("not a range"));
+    }
+}